@@ -0,0 +1,99 @@
+//! Derive macro for the Closable trait of close-file
+//!
+//! Use it through the `derive` feature of close-file, see close_file::Closable.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, parse_quote, Data, DeriveInput, Index, LitInt, Member, Type};
+
+/// Implements Closable by closing every field, see the `derive` feature of close-file
+#[proc_macro_derive(Closable, attributes(close))]
+pub fn derive_closable(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+struct Field {
+    ty: Type,
+    member: Member,
+    index: usize,
+    order: Option<i64>,
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let fields = match &input.data {
+        Data::Struct(data) => &data.fields,
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "Closable can only be derived for structs",
+            ))
+        }
+    };
+    let mut closed = Vec::new();
+    for (index, field) in fields.iter().enumerate() {
+        let mut skip = false;
+        let mut order = None;
+        for attr in field
+            .attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("close"))
+        {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("skip") {
+                    skip = true;
+                    Ok(())
+                } else if meta.path.is_ident("order") {
+                    order = Some(meta.value()?.parse::<LitInt>()?.base10_parse()?);
+                    Ok(())
+                } else {
+                    Err(meta.error("expected `skip` or `order = N`"))
+                }
+            })?;
+        }
+        if skip {
+            continue;
+        }
+        let member = match &field.ident {
+            Some(ident) => Member::Named(ident.clone()),
+            None => Member::Unnamed(Index::from(index)),
+        };
+        closed.push(Field {
+            ty: field.ty.clone(),
+            member,
+            index,
+            order,
+        });
+    }
+    // Fields without an order are closed after the ordered ones, in declaration order
+    closed.sort_by_key(|field| (field.order.is_none(), field.order));
+
+    let closes = closed.iter().map(|field| {
+        let (member, index) = (&field.member, field.index);
+        quote! { (#index, ::close_file::Closable::close(self.#member)) }
+    });
+    let name = &input.ident;
+    // Requires the closed fields to be Closable, so generic fields work without bounds on the
+    // struct
+    let mut generics = input.generics.clone();
+    let where_clause = generics.make_where_clause();
+    for field in &closed {
+        let ty = &field.ty;
+        where_clause
+            .predicates
+            .push(parse_quote!(#ty: ::close_file::Closable));
+    }
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::close_file::Closable for #name #ty_generics #where_clause {
+            #[track_caller]
+            fn close(self) -> ::std::result::Result<(), ::close_file::CloseError> {
+                ::close_file::__private::close_fields([#(#closes),*])
+            }
+        }
+    })
+}
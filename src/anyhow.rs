@@ -0,0 +1,23 @@
+//! Integration with anyhow
+//!
+//! OBS: This module is only available with the `anyhow` feature
+
+use crate::CloseError;
+use std::path::Path;
+
+/// Converts close results into anyhow results with the path and stage as context
+pub trait CloseResultExt<T> {
+    /// Attaches the path to the error and wraps it into an anyhow::Error with a context message
+    /// like "failed to close out.txt during sync"
+    fn close_context<P: AsRef<Path>>(self, path: P) -> ::anyhow::Result<T>;
+}
+
+impl<T> CloseResultExt<T> for Result<T, CloseError> {
+    fn close_context<P: AsRef<Path>>(self, path: P) -> ::anyhow::Result<T> {
+        self.map_err(|e| {
+            let path = path.as_ref();
+            let message = crate::error::context_message(path, e.stage());
+            ::anyhow::Error::new(e.with_path(path)).context(message)
+        })
+    }
+}
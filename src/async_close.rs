@@ -0,0 +1,43 @@
+use crate::CloseError;
+use std::future::Future;
+
+/// The async counterpart of Closable
+///
+/// Async runtimes have no async Drop, so their files and sockets are closed when dropped and close
+/// errors are discarded, just like for std::fs::File. Implementations close the resource, e.g. on
+/// the blocking thread pool of the runtime, and report the error of the OS.
+///
+/// ```
+/// use close_file::{AsyncClosable, CloseError};
+///
+/// async fn finish<T: AsyncClosable>(output: T) -> Result<(), CloseError> {
+///     output.close().await
+/// }
+/// ```
+pub trait AsyncClosable {
+    /// Closes the resource, reporting errors
+    ///
+    /// The future is Send, so it can be spawned on multi-threaded runtimes. The location of the
+    /// error is where close() was called, not where the future was polled.
+    #[track_caller]
+    fn close(self) -> impl Future<Output = Result<(), CloseError>> + Send;
+}
+
+/// Closes the value if there is one, None is closed successfully
+impl<T: AsyncClosable> AsyncClosable for Option<T> {
+    #[track_caller]
+    fn close(self) -> impl Future<Output = Result<(), CloseError>> + Send {
+        // Not Option::map(), the closure would hide the location of the caller
+        #[allow(clippy::manual_map)]
+        let future = match self {
+            Some(value) => Some(value.close()),
+            None => None,
+        };
+        async move {
+            match future {
+                Some(future) => future.await,
+                None => Ok(()),
+            }
+        }
+    }
+}
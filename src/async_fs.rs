@@ -0,0 +1,102 @@
+//! Integration with async-fs, the file system crate of smol
+//!
+//! async_fs::File doesn't give its std file back and closes it unchecked when dropped. To report
+//! errors, a duplicate of its descriptor/handle is synced and closed first, so the unchecked close
+//! of the original only releases the file after the data was written back.
+//!
+//! OBS: This module is only available with the `async-fs` feature
+
+use crate::sync::close_file_with_options;
+use crate::{AsyncClosable, CloseError, CloseOptions, CloseStage};
+use ::futures_lite::AsyncWriteExt;
+use std::future::Future;
+use std::panic::Location;
+use std::{fs, io};
+
+/// Syncing before closing, the async counterpart of SyncClosable
+///
+/// ```no_run
+/// use close_file::async_fs::FileExt;
+/// use close_file::{CloseOptions, Durability};
+/// use futures_lite::AsyncWriteExt;
+///
+/// # async fn write() {
+/// let mut f = async_fs::File::create("temp").await.unwrap();
+/// f.write_all(b"Hello, world!").await.unwrap();
+/// f.close_with_options(&CloseOptions::new().durability(Durability::Sync))
+///     .await
+///     .unwrap();
+/// # }
+/// ```
+pub trait FileExt {
+    /// Flushes the file, then applies the options and closes it with blocking::unblock()
+    ///
+    /// Errors are the same as for SyncClosable::close_with_options(), a failed sync owns the
+    /// duplicate. If the flush fails, the file is closed anyway, discarding errors closing it, and
+    /// the error has the stage CloseStage::Flush.
+    #[track_caller]
+    fn close_with_options(
+        self,
+        options: &CloseOptions,
+    ) -> impl Future<Output = Result<(), CloseError>> + Send + use<Self>;
+}
+
+impl FileExt for ::async_fs::File {
+    #[track_caller]
+    fn close_with_options(
+        mut self,
+        options: &CloseOptions,
+    ) -> impl Future<Output = Result<(), CloseError>> + Send + use<> {
+        let location = Location::caller();
+        let options = *options;
+        async move {
+            let flushed = self.flush().await;
+            let result = ::blocking::unblock(move || {
+                let result = match (flushed, duplicate(&self)) {
+                    (Ok(()), Ok(duplicate)) => {
+                        close_file_with_options(duplicate, &options).map(|_| ())
+                    }
+                    (Err(e), duplicate) => {
+                        let _ = duplicate.map(crate::Closable::close);
+                        Err(CloseError::detached(e, CloseStage::Flush))
+                    }
+                    (Ok(()), Err(e)) => Err(CloseError::detached(e, CloseStage::Close)),
+                };
+                // Closes the original after the duplicate, discarding errors
+                drop(self);
+                result
+            })
+            .await;
+            result.map_err(|mut e| {
+                e.set_location(location);
+                e
+            })
+        }
+    }
+}
+
+/// Flushes the file, then closes it with blocking::unblock()
+///
+/// This is the same as FileExt::close_with_options() with the default options.
+impl AsyncClosable for ::async_fs::File {
+    #[track_caller]
+    fn close(self) -> impl Future<Output = Result<(), CloseError>> + Send {
+        self.close_with_options(&CloseOptions::new())
+    }
+}
+
+/// Duplicates the descriptor of the file
+#[cfg(unix)]
+fn duplicate(file: &::async_fs::File) -> io::Result<fs::File> {
+    use std::os::unix::io::AsFd;
+
+    Ok(fs::File::from(file.as_fd().try_clone_to_owned()?))
+}
+
+/// Duplicates the handle of the file
+#[cfg(windows)]
+fn duplicate(file: &::async_fs::File) -> io::Result<fs::File> {
+    use std::os::windows::io::AsHandle;
+
+    Ok(fs::File::from(file.as_handle().try_clone_to_owned()?))
+}
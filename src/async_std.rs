@@ -0,0 +1,99 @@
+//! Integration with async-std
+//!
+//! async_std::fs::File flushes when dropped, blocking the executor, and errors of flushing and
+//! closing it are discarded.
+//!
+//! OBS: This module is only available with the `async-std` feature
+
+use crate::sync::close_file_with_options;
+use crate::{AsyncClosable, Closable, CloseError, CloseOptions, CloseStage};
+use ::async_std::io::WriteExt;
+use ::async_std::task;
+use std::fs;
+use std::future::Future;
+use std::panic::Location;
+
+/// Syncing before closing, the async counterpart of SyncClosable
+///
+/// ```no_run
+/// use async_std::io::WriteExt;
+/// use close_file::async_std::FileExt;
+/// use close_file::{CloseOptions, Durability};
+///
+/// # async fn write() {
+/// let mut f = async_std::fs::File::create("temp").await.unwrap();
+/// f.write_all(b"Hello, world!").await.unwrap();
+/// f.close_with_options(&CloseOptions::new().durability(Durability::Sync))
+///     .await
+///     .unwrap();
+/// # }
+/// ```
+pub trait FileExt {
+    /// Flushes the file, then applies the options and closes it on the blocking executor
+    ///
+    /// Errors are the same as for SyncClosable::close_with_options(). If the flush fails, the file
+    /// is closed anyway, discarding errors closing it, and the error has the stage
+    /// CloseStage::Flush.
+    #[track_caller]
+    fn close_with_options(
+        self,
+        options: &CloseOptions,
+    ) -> impl Future<Output = Result<(), CloseError>> + Send + use<Self>;
+}
+
+impl FileExt for ::async_std::fs::File {
+    #[track_caller]
+    fn close_with_options(
+        mut self,
+        options: &CloseOptions,
+    ) -> impl Future<Output = Result<(), CloseError>> + Send + use<> {
+        let location = Location::caller();
+        let options = *options;
+        async move {
+            let flushed = self.flush().await;
+            let file = into_std(self);
+            let result = match flushed {
+                Ok(()) => {
+                    task::spawn_blocking(move || {
+                        close_file_with_options(file, &options).map(|_| ())
+                    })
+                    .await
+                }
+                Err(e) => {
+                    let _ = task::spawn_blocking(move || file.close()).await;
+                    Err(CloseError::detached(e, CloseStage::Flush))
+                }
+            };
+            result.map_err(|mut e| {
+                e.set_location(location);
+                e
+            })
+        }
+    }
+}
+
+/// Flushes the file, then closes it on the blocking executor
+///
+/// This is the same as FileExt::close_with_options() with the default options.
+impl AsyncClosable for ::async_std::fs::File {
+    #[track_caller]
+    fn close(self) -> impl Future<Output = Result<(), CloseError>> + Send {
+        self.close_with_options(&CloseOptions::new())
+    }
+}
+
+/// Takes the std file out of the flushed file
+#[cfg(unix)]
+fn into_std(file: ::async_std::fs::File) -> fs::File {
+    use std::os::unix::io::{FromRawFd, IntoRawFd};
+
+    unsafe { fs::File::from_raw_fd(file.into_raw_fd()) }
+}
+
+/// Takes the std file out of the flushed file
+#[cfg(windows)]
+fn into_std(file: ::async_std::fs::File) -> fs::File {
+    use std::os::windows::io::{FromRawHandle, IntoRawHandle};
+
+    unsafe { fs::File::from_raw_handle(file.into_raw_handle()) }
+}
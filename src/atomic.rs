@@ -0,0 +1,395 @@
+use crate::closer::ErrorCallback;
+use crate::{imp, sync_parent_dir, Closable, CloseError, CloseStage, SyncClosable};
+use std::ffi::OsString;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::{fmt, fs, process};
+
+pub(crate) static TEMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Options for creating an AtomicWriteFile
+///
+/// ```
+/// use close_file::{AtomicWriteOptions, Closable};
+///
+/// let path = std::env::temp_dir().join("close-file-atomic-options-doc");
+/// let f = AtomicWriteOptions::new().anonymous_temp(true).create(&path).unwrap();
+/// f.close().unwrap();
+/// # std::fs::remove_file(&path).unwrap();
+/// ```
+#[derive(Clone, Default)]
+pub struct AtomicWriteOptions {
+    anonymous_temp: bool,
+    backup: Option<OsString>,
+    temp_dir: Option<PathBuf>,
+    on_drop_error: Option<ErrorCallback>,
+}
+
+impl AtomicWriteOptions {
+    /// Creates options for a named temporary file
+    pub fn new() -> Self {
+        AtomicWriteOptions::default()
+    }
+
+    /// Sets whether to write to an anonymous temporary file where supported
+    ///
+    /// On Linux the temporary file is created with O_TMPFILE, so it has no name and disappears on
+    /// a crash, and is only linked into the directory when closing. If the file system doesn't
+    /// support O_TMPFILE, or on other platforms, a named temporary file is used.
+    pub fn anonymous_temp(mut self, anonymous_temp: bool) -> Self {
+        self.anonymous_temp = anonymous_temp;
+        self
+    }
+
+    /// Sets the suffix of a backup of the target that is kept when committing
+    ///
+    /// The existing target is preserved at its path with the suffix appended, e.g. "config.toml"
+    /// is kept as "config.toml.bak" with the suffix ".bak", replacing an older backup. The target
+    /// is durably replaced, so if the new content turns out to be wrong, the backup can be
+    /// restored.
+    ///
+    /// On unix the backup is a hard link to the old target, so readers never see the target
+    /// missing. On file systems without hard links the target is renamed. On windows ReplaceFileW
+    /// creates the backup.
+    pub fn backup<S: Into<OsString>>(mut self, suffix: S) -> Self {
+        self.backup = Some(suffix.into());
+        self
+    }
+
+    /// Sets a callback for errors when an uncommitted file is dropped
+    ///
+    /// Dropping aborts the file like abort(), but can't return errors closing and removing the
+    /// temporary file, so they are passed to the callback instead. Without a callback they are
+    /// passed to the handler of set_drop_close_error_handler().
+    pub fn on_drop_error<F: Fn(&CloseError) + Send + Sync + 'static>(
+        mut self,
+        callback: F,
+    ) -> Self {
+        self.on_drop_error = Some(Arc::new(callback));
+        self
+    }
+
+    /// Sets the directory the temporary file is created in, instead of next to the target
+    pub(crate) fn temp_dir(mut self, dir: &Path) -> Self {
+        self.temp_dir = Some(dir.to_path_buf());
+        self
+    }
+
+    /// Creates the temporary file for replacing the file at the given path
+    ///
+    /// If the target already exists, its permissions are copied to the temporary file.
+    pub fn create<P: AsRef<Path>>(&self, path: P) -> io::Result<AtomicWriteFile> {
+        let path = path.as_ref().to_path_buf();
+        if path.file_name().is_none() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "path has no file name",
+            ));
+        }
+        let temp_dir = self.temp_dir.as_deref();
+        let anonymous = match self.anonymous_temp {
+            true => imp::create_anonymous(temp_dir.unwrap_or_else(|| parent_dir(&path)))?,
+            false => None,
+        };
+        let (file, temp_path) = match anonymous {
+            Some(file) => (file, None),
+            None => {
+                let (file, temp_path) = create_temp(&path, temp_dir)?;
+                (file, Some(temp_path))
+            }
+        };
+        let backup_path = self.backup.as_ref().map(|suffix| {
+            let mut name = path.file_name().unwrap_or_default().to_os_string();
+            name.push(suffix);
+            path.with_file_name(name)
+        });
+        let file = AtomicWriteFile {
+            file: Some(file),
+            path,
+            temp_path,
+            backup_path,
+            temp_dir: self.temp_dir.clone(),
+            done: false,
+            on_drop_error: self.on_drop_error.clone(),
+        };
+        // On failure the file is dropped, which removes the temporary file
+        if let Ok(metadata) = fs::metadata(&file.path) {
+            file.as_file().set_permissions(metadata.permissions())?;
+        }
+        Ok(file)
+    }
+}
+
+impl fmt::Debug for AtomicWriteOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AtomicWriteOptions")
+            .field("anonymous_temp", &self.anonymous_temp)
+            .field("backup", &self.backup)
+            .field("temp_dir", &self.temp_dir)
+            .field("on_drop_error", &self.on_drop_error.is_some())
+            .finish()
+    }
+}
+
+/// A file that replaces its target atomically when it is closed
+///
+/// The content is written to a temporary file next to the target. Closing syncs and closes the
+/// temporary file, renames it over the target and syncs the directory, so readers either see the
+/// old or the complete new file, even after a crash. On windows the target is replaced with
+/// ReplaceFileW, which keeps its ACLs, attributes and alternate data streams. If any step fails,
+/// the target is not touched and the temporary file is removed.
+///
+/// Closing the file commits it, see commit(). Use abort() to discard the content instead, e.g.
+/// when producing it failed midway. Dropping the file without committing it also aborts it.
+///
+/// ```
+/// use close_file::{AtomicWriteFile, Closable};
+/// use std::io::Write;
+///
+/// let path = std::env::temp_dir().join("close-file-atomic-doc");
+/// let mut f = AtomicWriteFile::create(&path).unwrap();
+/// f.write_all(b"Hello, world!").unwrap();
+/// f.close().unwrap();
+/// # std::fs::remove_file(&path).unwrap();
+/// ```
+pub struct AtomicWriteFile {
+    file: Option<fs::File>,
+    path: PathBuf,
+    temp_path: Option<PathBuf>,
+    backup_path: Option<PathBuf>,
+    temp_dir: Option<PathBuf>,
+    // Committed or aborted, so there is nothing left to clean up
+    done: bool,
+    on_drop_error: Option<ErrorCallback>,
+}
+
+impl AtomicWriteFile {
+    /// Creates the temporary file for replacing the file at the given path
+    ///
+    /// This is the same as AtomicWriteOptions::new().create(path).
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<AtomicWriteFile> {
+        AtomicWriteOptions::new().create(path)
+    }
+
+    /// Returns options to configure how the file is created
+    pub fn options() -> AtomicWriteOptions {
+        AtomicWriteOptions::new()
+    }
+
+    /// The path of the file that is replaced when closing
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The path of the temporary file the content is written to
+    ///
+    /// Returns None for an anonymous temporary file, see AtomicWriteOptions::anonymous_temp().
+    pub fn temp_path(&self) -> Option<&Path> {
+        self.temp_path.as_deref()
+    }
+
+    /// The path the target is backed up to when committing, see AtomicWriteOptions::backup()
+    pub fn backup_path(&self) -> Option<&Path> {
+        self.backup_path.as_deref()
+    }
+
+    /// The temporary file
+    pub fn as_file(&self) -> &fs::File {
+        self.file.as_ref().expect("file is only taken when closing")
+    }
+
+    fn file_mut(&mut self) -> &mut fs::File {
+        self.file.as_mut().expect("file is only taken when closing")
+    }
+
+    /// Syncs and closes the temporary file, renames it over the target and syncs the directory
+    ///
+    /// If a backup is configured, the target is backed up right before it is replaced. The error
+    /// has the path of the target and the stage that failed. The temporary file is closed and
+    /// removed on failure, so the error never owns the file.
+    #[track_caller]
+    pub fn commit(mut self) -> Result<(), CloseError> {
+        self.prepare()?;
+        self.install()?;
+        if let Err(e) = sync_parent_dir(&self.path) {
+            return Err(CloseError::detached(e, CloseStage::SyncDir).with_path(&self.path));
+        }
+        Ok(())
+    }
+
+    /// Closes and removes the temporary file, leaving the target untouched
+    ///
+    /// The error has the path of the temporary file, or of the target for an anonymous temporary
+    /// file. If closing fails, the temporary file is still removed and the close error returned.
+    #[track_caller]
+    pub fn abort(mut self) -> Result<(), CloseError> {
+        self.discard()
+    }
+
+    /// Syncs and closes the temporary file, linking it first if it is anonymous
+    #[track_caller]
+    pub(crate) fn prepare(&mut self) -> Result<(), CloseError> {
+        if self.temp_path.is_none() {
+            match link_temp(self.as_file(), &self.path, self.temp_dir.as_deref()) {
+                Ok(temp_path) => self.temp_path = Some(temp_path),
+                Err(e) => {
+                    return Err(CloseError::detached(e, CloseStage::Rename).with_path(&self.path))
+                }
+            }
+        }
+        let file = self.file.take().expect("file is only taken when closing");
+        if let Err(mut e) = file.close_synced() {
+            drop(e.take_file());
+            return Err(e.with_path(&self.path));
+        }
+        Ok(())
+    }
+
+    /// Replaces the target with the prepared temporary file
+    #[track_caller]
+    pub(crate) fn install(&mut self) -> Result<(), CloseError> {
+        let temp_path = self.temp_path.as_ref().expect("file is prepared");
+        if let Err(e) = imp::replace_file(temp_path, &self.path, self.backup_path.as_deref()) {
+            return Err(CloseError::detached(e, CloseStage::Rename).with_path(&self.path));
+        }
+        self.done = true;
+        Ok(())
+    }
+
+    /// Closes the temporary file if it is still open and removes it, unless already done
+    #[track_caller]
+    fn discard(&mut self) -> Result<(), CloseError> {
+        if self.done {
+            return Ok(());
+        }
+        self.done = true;
+        let path = self.temp_path.as_ref().unwrap_or(&self.path);
+        let closed = match self.file.take() {
+            Some(file) => file.close_with_path(path),
+            None => Ok(()),
+        };
+        if let Some(temp_path) = &self.temp_path {
+            if let Err(e) = fs::remove_file(temp_path) {
+                closed?;
+                return Err(CloseError::detached(e, CloseStage::Remove).with_path(temp_path));
+            }
+        }
+        closed
+    }
+}
+
+/// The directory containing the path
+fn parent_dir(path: &Path) -> &Path {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    }
+}
+
+/// Returns a new temporary path for the given path, in the given directory or next to the path
+fn temp_path(path: &Path, dir: Option<&Path>) -> PathBuf {
+    let mut temp_name = OsString::from(".");
+    temp_name.push(path.file_name().unwrap_or_default());
+    temp_name.push(format!(
+        ".{}.{}.tmp",
+        process::id(),
+        TEMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    match dir {
+        Some(dir) => dir.join(temp_name),
+        None => path.with_file_name(temp_name),
+    }
+}
+
+/// Creates a new temporary file for the given path
+fn create_temp(path: &Path, dir: Option<&Path>) -> io::Result<(fs::File, PathBuf)> {
+    loop {
+        let temp_path = temp_path(path, dir);
+        match fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&temp_path)
+        {
+            Ok(file) => return Ok((file, temp_path)),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Links an anonymous temporary file to a new temporary path for the given path
+fn link_temp(file: &fs::File, path: &Path, dir: Option<&Path>) -> io::Result<PathBuf> {
+    loop {
+        let temp_path = temp_path(path, dir);
+        match imp::link_anonymous(file, &temp_path) {
+            Ok(()) => return Ok(temp_path),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+impl Write for AtomicWriteFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.file_mut().write(buf)
+    }
+
+    fn write_vectored(&mut self, bufs: &[io::IoSlice<'_>]) -> io::Result<usize> {
+        self.file_mut().write_vectored(bufs)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file_mut().flush()
+    }
+}
+
+impl Closable for AtomicWriteFile {
+    /// Commits the file, see commit()
+    #[track_caller]
+    fn close(self) -> Result<(), CloseError> {
+        self.commit()
+    }
+}
+
+impl fmt::Debug for AtomicWriteFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AtomicWriteFile")
+            .field("file", &self.file)
+            .field("path", &self.path)
+            .field("temp_path", &self.temp_path)
+            .field("backup_path", &self.backup_path)
+            .finish()
+    }
+}
+
+impl Drop for AtomicWriteFile {
+    fn drop(&mut self) {
+        if let Err(e) = self.discard() {
+            match &self.on_drop_error {
+                Some(on_drop_error) => on_drop_error(&e),
+                None => crate::hook::report_drop_error::<Self>(e),
+            }
+        }
+    }
+}
+
+/// Writes the contents to the file at the given path atomically
+///
+/// This is a replacement for std::fs::write() for files that must never be seen partially
+/// written, like configuration or state files, see AtomicWriteFile. A close error is returned as
+/// io::Error with the CloseError as inner error.
+///
+/// ```
+/// let path = std::env::temp_dir().join("close-file-write-atomic-doc");
+/// close_file::write_atomic(&path, "key = value").unwrap();
+/// # std::fs::remove_file(&path).unwrap();
+/// ```
+#[track_caller]
+pub fn write_atomic<P: AsRef<Path>, C: AsRef<[u8]>>(path: P, contents: C) -> io::Result<()> {
+    let mut file = AtomicWriteFile::create(path)?;
+    file.write_all(contents.as_ref())?;
+    file.close()?;
+    Ok(())
+}
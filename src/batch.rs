@@ -0,0 +1,27 @@
+use crate::{imp, CloseError, Durability, SyncClosable};
+use std::fs;
+
+/// Syncs and closes all files, syncing each file system only once where possible
+///
+/// On Linux a single syncfs(2) is issued per file system instead of fsync(2) per file, which is a
+/// lot faster for many files, e.g. at shutdown. Note that syncfs(2) also syncs data written by
+/// others to the same file system. syncfs(2) only reports errors per file system, so every file is
+/// then still fdatasync(2)ed before it's closed, which is cheap once its data is clean and reports
+/// the write-back errors of that file. If syncfs(2) fails, the files on that file system are synced
+/// one by one with close_synced(). Elsewhere every file is closed with close_synced().
+///
+/// The results are in the order of the files.
+#[track_caller]
+pub fn close_all_synced<I: IntoIterator<Item = fs::File>>(files: I) -> Vec<Result<(), CloseError>> {
+    let files: Vec<fs::File> = files.into_iter().collect();
+    let synced = imp::sync_filesystems(&files);
+    let mut results = Vec::with_capacity(files.len());
+    for (file, synced) in files.into_iter().zip(synced) {
+        if synced {
+            results.push(file.close_with(Durability::DataSync));
+        } else {
+            results.push(file.close_synced());
+        }
+    }
+    results
+}
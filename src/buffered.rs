@@ -0,0 +1,110 @@
+use crate::{
+    Closable, CloseError, CloseOptions, CloseStage, SyncClosable, TryClosable, TryCloseError,
+};
+use std::io::{BufReader, BufWriter, LineWriter, Write};
+
+/// Flushes the buffer, returning the inner writer
+///
+/// If the flush fails, the error keeps the inner writer and holds the data that is still buffered.
+#[track_caller]
+fn flush<W: Write + Send + 'static>(mut writer: BufWriter<W>) -> Result<W, CloseError> {
+    let flushed = writer.flush();
+    let (inner, buffer) = writer.into_parts();
+    match flushed {
+        Ok(()) => Ok(inner),
+        Err(e) => {
+            let mut err = CloseError::retained(e, inner, CloseStage::Flush);
+            // The buffer is only unavailable if a write panicked
+            err.set_unwritten(buffer.unwrap_or_else(|panicked| panicked.into_inner()));
+            Err(err)
+        }
+    }
+}
+
+/// Flushes the buffer, then closes the inner writer
+///
+/// Stacks of writers, e.g. a BufWriter around a compressor around a file, are closed from the
+/// outside in with a single call. If the flush fails, the inner writer is not closed and the error
+/// of the stage CloseStage::Flush keeps it, see CloseError::take_retained() and, for a
+/// std::fs::File, CloseError::into_file(). The data that was not written is in
+/// CloseError::unwritten().
+impl<W: Write + Closable + Send + 'static> Closable for BufWriter<W> {
+    #[track_caller]
+    fn close(self) -> Result<(), CloseError> {
+        flush(self)?.close()
+    }
+}
+
+impl<W: Write + SyncClosable + Send + 'static> SyncClosable for BufWriter<W> {
+    #[track_caller]
+    fn close_with_options(self, options: &CloseOptions) -> Result<(), CloseError> {
+        flush(self)?.close_with_options(options)
+    }
+}
+
+/// Flushes the buffer, then closes the inner writer
+///
+/// If the flush fails, the writer is handed back with the buffered data, so writing can be
+/// retried. If the inner writer is handed back, it is wrapped in a BufWriter of the same capacity
+/// again.
+impl<W: Write + TryClosable> TryClosable for BufWriter<W> {
+    #[track_caller]
+    fn try_close(mut self) -> Result<(), TryCloseError<Self>> {
+        if let Err(e) = self.flush() {
+            return Err(TryCloseError::Retained(self, e));
+        }
+        let capacity = self.capacity();
+        let (inner, _) = self.into_parts();
+        match inner.try_close() {
+            Ok(()) => Ok(()),
+            Err(e) => Err(e.map(|inner| BufWriter::with_capacity(capacity, inner))),
+        }
+    }
+}
+
+/// Flushes the buffer of the line writer, returning the inner writer
+///
+/// If the flush fails, the inner writer is dropped without reporting errors closing it, as
+/// LineWriter doesn't give it back.
+#[track_caller]
+fn flush_lines<W: Write>(writer: LineWriter<W>) -> Result<W, CloseError> {
+    match writer.into_inner() {
+        Ok(inner) => Ok(inner),
+        Err(e) => {
+            let (e, _) = e.into_parts();
+            Err(CloseError::detached(e, CloseStage::Flush))
+        }
+    }
+}
+
+/// Flushes the buffer, then closes the inner writer
+///
+/// If the flush fails, the error has the stage CloseStage::Flush.
+impl<W: Write + Closable> Closable for LineWriter<W> {
+    #[track_caller]
+    fn close(self) -> Result<(), CloseError> {
+        flush_lines(self)?.close()
+    }
+}
+
+impl<W: Write + SyncClosable> SyncClosable for LineWriter<W> {
+    #[track_caller]
+    fn close_with_options(self, options: &CloseOptions) -> Result<(), CloseError> {
+        flush_lines(self)?.close_with_options(options)
+    }
+}
+
+/// Closes the inner reader, discarding the buffered data
+impl<R: Closable> Closable for BufReader<R> {
+    #[track_caller]
+    fn close(self) -> Result<(), CloseError> {
+        self.into_inner().close()
+    }
+}
+
+impl<R: SyncClosable> SyncClosable for BufReader<R> {
+    #[track_caller]
+    fn close_with_options(self, options: &CloseOptions) -> Result<(), CloseError> {
+        self.into_inner().close_with_options(options)
+    }
+}
@@ -0,0 +1,216 @@
+use crate::{CloseError, CloseOptions, CloseStage, Durability, FailurePolicy, SyncClosable};
+use std::panic::Location;
+use std::sync::{mpsc, Arc};
+use std::time::Duration;
+use std::{fmt, io, thread};
+
+pub(crate) type ErrorCallback = Arc<dyn Fn(&CloseError) + Send + Sync>;
+
+/// A configurable close pipeline
+///
+/// Composes the durability level, retries, a timeout and an error callback, and can be reused to
+/// close any number of files.
+///
+/// ```
+/// use close_file::{Closer, Durability};
+/// use std::time::Duration;
+///
+/// let closer = Closer::new()
+///     .sync(Durability::Sync)
+///     .retry(3)
+///     .timeout(Duration::from_secs(30))
+///     .on_error(|e| eprintln!("{}", e));
+/// let f = std::fs::File::create("temp").unwrap();
+/// closer.close(f).unwrap();
+/// ```
+#[derive(Clone)]
+pub struct Closer {
+    options: CloseOptions,
+    retries: u32,
+    timeout: Option<Duration>,
+    on_failure: FailurePolicy,
+    on_error: Option<ErrorCallback>,
+}
+
+impl Closer {
+    /// Creates a pipeline that closes without syncing, retrying or a timeout
+    pub fn new() -> Self {
+        Closer {
+            options: CloseOptions::new(),
+            retries: 0,
+            timeout: None,
+            on_failure: FailurePolicy::Keep,
+            on_error: None,
+        }
+    }
+
+    /// Sets the durability level the file is synced with before closing
+    pub fn sync(mut self, durability: Durability) -> Self {
+        self.options = self.options.durability(durability);
+        self
+    }
+
+    /// Sets whether to choose the durability level by the file system of each file
+    ///
+    /// See CloseOptions::auto_durability().
+    pub fn auto_durability(mut self, auto_durability: bool) -> Self {
+        self.options = self.options.auto_durability(auto_durability);
+        self
+    }
+
+    /// Sets whether to start writing back dirty pages before syncing and closing
+    ///
+    /// See CloseOptions::start_writeback().
+    pub fn start_writeback(mut self, start_writeback: bool) -> Self {
+        self.options = self.options.start_writeback(start_writeback);
+        self
+    }
+
+    /// Sets whether to drop the pages of the file from the page cache after syncing
+    ///
+    /// See CloseOptions::drop_cache().
+    pub fn drop_cache(mut self, drop_cache: bool) -> Self {
+        self.options = self.options.drop_cache(drop_cache);
+        self
+    }
+
+    /// Sets how often closing is retried
+    ///
+    /// Only errors of the close stage that are retryable (see CloseError::is_retryable()) are
+    /// retried. Failed syncs are never retried, as a second sync may report success even though
+    /// the data was lost.
+    pub fn retry(mut self, retries: u32) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Sets how long to wait for the file to be synced and closed
+    ///
+    /// Syncing and closing may block for a long time, e.g. on network file systems. With a timeout,
+    /// the file is closed on a separate thread. If that takes longer than the timeout, an error of
+    /// the kind CloseErrorKind::TimedOut is returned, and the file continues to be closed in the
+    /// background. An error that happens after the timeout is passed to the on_error callback.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Sets what to do with the file when closing it failed
+    ///
+    /// The policy is applied after the retries and before the on_error callback. It needs the path
+    /// of the file, so it only works for files that know their path, e.g. close_file::fs::File.
+    /// For others, e.g. std::fs::File, the error reports a failed cleanup, see FailurePolicy.
+    pub fn on_failure(mut self, policy: FailurePolicy) -> Self {
+        self.on_failure = policy;
+        self
+    }
+
+    /// Sets a callback that is called with each error, before it is returned
+    pub fn on_error<F>(mut self, on_error: F) -> Self
+    where
+        F: Fn(&CloseError) + Send + Sync + 'static,
+    {
+        self.on_error = Some(Arc::new(on_error));
+        self
+    }
+
+    /// Closes the file with the configured pipeline
+    #[track_caller]
+    pub fn close<C>(&self, closable: C) -> Result<(), CloseError>
+    where
+        C: SyncClosable + Send + 'static,
+    {
+        let result = match self.timeout {
+            Some(timeout) => self.close_with_timeout(closable, timeout, Location::caller()),
+            None => self.close_now(closable),
+        };
+        if let Err(e) = &result {
+            self.report(e);
+        }
+        result
+    }
+
+    #[track_caller]
+    fn close_now<C: SyncClosable>(&self, closable: C) -> Result<(), CloseError> {
+        let mut retries = self.retries;
+        let mut result = closable.close_with_options(&self.options);
+        loop {
+            match result {
+                Err(e) if retries > 0 && e.stage() == CloseStage::Close && e.is_retryable() => {
+                    retries -= 1;
+                    result = e.retry();
+                }
+                Err(mut e) => {
+                    self.on_failure.apply(&mut e);
+                    return Err(e);
+                }
+                Ok(()) => return Ok(()),
+            }
+        }
+    }
+
+    #[track_caller]
+    fn close_with_timeout<C>(
+        &self,
+        closable: C,
+        timeout: Duration,
+        location: &'static Location<'static>,
+    ) -> Result<(), CloseError>
+    where
+        C: SyncClosable + Send + 'static,
+    {
+        let (sender, receiver) = mpsc::channel();
+        let closer = self.clone();
+        let spawned = thread::Builder::new()
+            .name("close-file".to_string())
+            .spawn(move || {
+                let result = closer.close_now(closable).map_err(|mut e| {
+                    e.set_location(location);
+                    e
+                });
+                if let Err(mpsc::SendError(Err(e))) = sender.send(result) {
+                    // The caller timed out and is gone
+                    closer.report(&e);
+                }
+            });
+        if let Err(e) = spawned {
+            return Err(CloseError::detached(e, CloseStage::Close));
+        }
+
+        match receiver.recv_timeout(timeout) {
+            Ok(result) => result,
+            Err(mpsc::RecvTimeoutError::Timeout) => Err(CloseError::detached(
+                io::Error::new(io::ErrorKind::TimedOut, "closing the file timed out"),
+                CloseStage::Close,
+            )),
+            Err(mpsc::RecvTimeoutError::Disconnected) => Err(CloseError::detached(
+                io::Error::other("closing the file panicked"),
+                CloseStage::Close,
+            )),
+        }
+    }
+
+    fn report(&self, e: &CloseError) {
+        if let Some(on_error) = &self.on_error {
+            on_error(e);
+        }
+    }
+}
+
+impl Default for Closer {
+    fn default() -> Self {
+        Closer::new()
+    }
+}
+
+impl fmt::Debug for Closer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Closer")
+            .field("options", &self.options)
+            .field("retries", &self.retries)
+            .field("timeout", &self.timeout)
+            .field("on_failure", &self.on_failure)
+            .field("on_error", &self.on_error.is_some())
+            .finish()
+    }
+}
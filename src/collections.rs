@@ -0,0 +1,180 @@
+use crate::{Closable, CloseError, CloseErrors};
+use std::collections::BTreeMap;
+use std::fs;
+
+/// Closes every resource, collecting the errors of all that failed
+///
+/// ```
+/// let files = vec![std::fs::File::create("temp").unwrap()];
+/// if let Err(errors) = close_file::close_all(files) {
+///     for (index, e) in errors {
+///         eprintln!("output {}: {}", index, e);
+///     }
+/// }
+/// ```
+#[track_caller]
+pub fn close_all<T: Closable, I: IntoIterator<Item = T>>(resources: I) -> Result<(), CloseErrors> {
+    let mut errors = CloseErrors::new();
+    for (index, resource) in resources.into_iter().enumerate() {
+        if let Err(e) = resource.close() {
+            errors.push(index, e);
+        }
+    }
+    errors.into_result()
+}
+
+/// Closes the resources until one fails
+///
+/// The resources after the failed one are dropped without checking their errors, e.g. as the
+/// output is discarded anyway. The errors hold the single error.
+#[track_caller]
+pub fn close_all_fail_fast<T: Closable, I: IntoIterator<Item = T>>(
+    resources: I,
+) -> Result<(), CloseErrors> {
+    let mut errors = CloseErrors::new();
+    for (index, resource) in resources.into_iter().enumerate() {
+        if let Err(e) = resource.close() {
+            errors.push(index, e);
+            break;
+        }
+    }
+    errors.into_result()
+}
+
+/// Closes every element, even if closing an earlier one failed
+///
+/// On failure the error holds the errors of all elements that failed to close, see
+/// CloseError::errors().
+impl<T: Closable> Closable for Vec<T> {
+    #[track_caller]
+    fn close(self) -> Result<(), CloseError> {
+        match close_all(self) {
+            Ok(()) => Ok(()),
+            Err(errors) => Err(CloseError::from_errors(errors)),
+        }
+    }
+}
+
+/// Closes every element like the impl for Vec
+impl<T: Closable, const N: usize> Closable for [T; N] {
+    #[track_caller]
+    fn close(self) -> Result<(), CloseError> {
+        match close_all(IntoIterator::into_iter(self)) {
+            Ok(()) => Ok(()),
+            Err(errors) => Err(CloseError::from_errors(errors)),
+        }
+    }
+}
+
+macro_rules! tuple_impl {
+    ($($name:ident $index:tt),+) => {
+        /// Closes every element like the impl for Vec, the indices are the positions in the tuple
+        impl<$($name: Closable),+> Closable for ($($name,)+) {
+            #[track_caller]
+            fn close(self) -> Result<(), CloseError> {
+                let mut errors = CloseErrors::new();
+                $(
+                    if let Err(e) = self.$index.close() {
+                        errors.push($index, e);
+                    }
+                )+
+                match errors.into_result() {
+                    Ok(()) => Ok(()),
+                    Err(errors) => Err(CloseError::from_errors(errors)),
+                }
+            }
+        }
+    };
+}
+
+tuple_impl!(A 0);
+tuple_impl!(A 0, B 1);
+tuple_impl!(A 0, B 1, C 2);
+tuple_impl!(A 0, B 1, C 2, D 3);
+tuple_impl!(A 0, B 1, C 2, D 3, E 4);
+tuple_impl!(A 0, B 1, C 2, D 3, E 4, F 5);
+tuple_impl!(A 0, B 1, C 2, D 3, E 4, F 5, G 6);
+tuple_impl!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7);
+
+/// Open files or other closables keyed by a label, e.g. the name of a shard
+///
+/// close_all() closes every entry and reports the result per key, so failures can be attributed
+/// exactly.
+///
+/// ```
+/// use close_file::FileSet;
+/// use std::io::Write;
+///
+/// let mut outputs = FileSet::new();
+/// outputs.insert("shard-0", std::fs::File::create("temp").unwrap());
+/// outputs.get_mut(&"shard-0").unwrap().write_all(b"Hello, world!").unwrap();
+/// for (shard, result) in outputs.close_all() {
+///     if let Err(e) = result {
+///         eprintln!("{}: {}", shard, e);
+///     }
+/// }
+/// ```
+#[derive(Debug)]
+pub struct FileSet<K, T = fs::File> {
+    entries: BTreeMap<K, T>,
+}
+
+impl<K: Ord, T: Closable> FileSet<K, T> {
+    /// Creates an empty set
+    pub fn new() -> Self {
+        FileSet {
+            entries: BTreeMap::new(),
+        }
+    }
+
+    /// Inserts the entry, returning the previous entry of the key, which is not closed
+    pub fn insert(&mut self, key: K, value: T) -> Option<T> {
+        self.entries.insert(key, value)
+    }
+
+    /// Returns the entry of the key
+    pub fn get(&self, key: &K) -> Option<&T> {
+        self.entries.get(key)
+    }
+
+    /// Returns the entry of the key mutably, e.g. to write to it
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut T> {
+        self.entries.get_mut(key)
+    }
+
+    /// Removes the entry of the key without closing it
+    pub fn remove(&mut self, key: &K) -> Option<T> {
+        self.entries.remove(key)
+    }
+
+    /// Returns the number of entries
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if there are no entries
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the keys in order
+    pub fn keys(&self) -> impl Iterator<Item = &K> {
+        self.entries.keys()
+    }
+
+    /// Closes every entry, even if closing an earlier one failed, returning the result per key
+    #[track_caller]
+    pub fn close_all(self) -> BTreeMap<K, Result<(), CloseError>> {
+        let mut results = BTreeMap::new();
+        for (key, value) in self.entries {
+            results.insert(key, value.close());
+        }
+        results
+    }
+}
+
+impl<K: Ord, T: Closable> Default for FileSet<K, T> {
+    fn default() -> Self {
+        FileSet::new()
+    }
+}
@@ -0,0 +1,131 @@
+use crate::{imp, Closable, CloseError, CloseOptions, Durability, SyncClosable};
+use std::path::{Path, PathBuf};
+use std::{fs, io};
+
+/// An open directory that can be synced and closed with error reporting
+///
+/// Syncing a directory makes the creation, renaming and removal of the files in it durable. Errors
+/// when closing carry the path of the directory.
+///
+/// ```
+/// use close_file::{Closable, Dir};
+///
+/// let dir = Dir::open(std::env::temp_dir()).unwrap();
+/// dir.sync().unwrap();
+/// dir.close().unwrap();
+/// ```
+#[derive(Debug)]
+pub struct Dir {
+    file: fs::File,
+    path: PathBuf,
+}
+
+impl Dir {
+    /// Opens the directory at the given path
+    ///
+    /// Fails if the path is not a directory.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Dir> {
+        let path = path.as_ref();
+        Ok(Dir {
+            file: imp::open_dir(path)?,
+            path: path.to_path_buf(),
+        })
+    }
+
+    /// The path the directory was opened with
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Syncs the directory entries to the storage device
+    ///
+    /// This uses the same system calls as close_synced(). On windows directory handles can't be
+    /// flushed without write access, so this is a no-op there.
+    pub fn sync(&self) -> io::Result<()> {
+        imp::sync(&self.file, Durability::FullSync)
+    }
+
+    /// Converts the directory into the underlying file
+    pub fn into_file(self) -> fs::File {
+        self.file
+    }
+}
+
+impl Closable for Dir {
+    #[track_caller]
+    fn close(self) -> Result<(), CloseError> {
+        self.file.close_with_path(self.path)
+    }
+}
+
+impl SyncClosable for Dir {
+    #[track_caller]
+    fn close_with_options(self, options: &CloseOptions) -> Result<(), CloseError> {
+        let path = self.path;
+        self.file
+            .close_with_options(options)
+            .map_err(|e| e.with_path(path))
+    }
+}
+
+#[cfg(unix)]
+impl std::os::unix::io::AsRawFd for Dir {
+    fn as_raw_fd(&self) -> std::os::unix::io::RawFd {
+        self.file.as_raw_fd()
+    }
+}
+
+#[cfg(windows)]
+impl std::os::windows::io::AsRawHandle for Dir {
+    fn as_raw_handle(&self) -> std::os::windows::io::RawHandle {
+        self.file.as_raw_handle()
+    }
+}
+
+/// Syncs the directory containing the given path
+///
+/// Newly created, renamed and removed files are only durable once the directory entry is synced,
+/// syncing the file itself is not enough. The directory is opened, synced like close_synced() and
+/// closed, all errors are returned. A close error is returned as io::Error with the CloseError as
+/// inner error.
+///
+/// On windows this is a no-op, as NTFS makes directory changes durable with the file metadata and
+/// directories can't be flushed like files.
+pub fn sync_parent_dir<P: AsRef<Path>>(path: P) -> io::Result<()> {
+    if cfg!(windows) {
+        return Ok(());
+    }
+    Dir::open(parent_dir(path.as_ref()))?.close_synced()?;
+    Ok(())
+}
+
+/// Renames a file and syncs the directories involved, so the rename survives a crash
+///
+/// After std::fs::rename() the directory of the destination is synced, and the directory of the
+/// source if it is a different one, see sync_parent_dir(). On windows MoveFileExW with
+/// MOVEFILE_WRITE_THROUGH is used instead, which only returns once the rename is on disk. The
+/// destination is replaced if it exists.
+pub fn rename_durable<P: AsRef<Path>, Q: AsRef<Path>>(from: P, to: Q) -> io::Result<()> {
+    let (from, to) = (from.as_ref(), to.as_ref());
+    #[cfg(windows)]
+    return imp::rename_write_through(from, to);
+    #[cfg(unix)]
+    {
+        fs::rename(from, to)?;
+        sync_parent_dir(to)?;
+        if parent_dir(from) != parent_dir(to) {
+            sync_parent_dir(from)?;
+        }
+        Ok(())
+    }
+}
+
+/// The directory containing the path, the path itself for the root
+#[cfg_attr(windows, allow(dead_code))]
+fn parent_dir(path: &Path) -> &Path {
+    match path.parent() {
+        Some(parent) if parent.as_os_str().is_empty() => Path::new("."),
+        Some(parent) => parent,
+        None => path,
+    }
+}
@@ -0,0 +1,133 @@
+use crate::{imp, Closable, CloseError, CloseOptions, Durability, SyncClosable};
+use std::fs;
+use std::io::{self, Read, Seek, Write};
+use std::ops::{Deref, DerefMut};
+
+/// A file that is synced before it is closed, but only if it was written to
+///
+/// Writes since the last sync mark the file as dirty. close() syncs a dirty file with the
+/// configured durability level and only closes a clean one, so files that are only read don't pay
+/// for a sync.
+///
+/// Writes through the std::fs::File it dereferences to, like set_len(), are not tracked, call
+/// mark_dirty() after them.
+///
+/// ```
+/// use close_file::{Closable, Durability, DurableFile};
+/// use std::io::Write;
+///
+/// let mut f = DurableFile::new(std::fs::File::create("temp").unwrap(), Durability::Sync);
+/// f.write_all(b"Hello, world!").unwrap();
+/// assert!(f.is_dirty());
+/// f.close().unwrap();
+/// ```
+#[derive(Debug)]
+pub struct DurableFile {
+    inner: fs::File,
+    durability: Durability,
+    dirty: bool,
+}
+
+impl DurableFile {
+    /// Wraps a file that is synced with the given durability level when it is closed dirty
+    pub fn new(inner: fs::File, durability: Durability) -> Self {
+        DurableFile {
+            inner,
+            durability,
+            dirty: false,
+        }
+    }
+
+    /// The durability level the file is synced with
+    pub fn durability(&self) -> Durability {
+        self.durability
+    }
+
+    /// Whether the file was written to since the last sync
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    /// Marks the file as written to, for writes that are not tracked
+    pub fn mark_dirty(&mut self) {
+        self.dirty = true;
+    }
+
+    /// Syncs the file with the configured durability level if it is dirty
+    pub fn sync(&mut self) -> io::Result<()> {
+        if self.dirty && self.durability != Durability::None && imp::is_syncable(&self.inner) {
+            imp::sync(&self.inner, self.durability)?;
+        }
+        self.dirty = false;
+        Ok(())
+    }
+
+    /// Converts the file into a std::fs::File, without syncing it
+    pub fn into_inner(self) -> fs::File {
+        self.inner
+    }
+}
+
+impl Deref for DurableFile {
+    type Target = fs::File;
+
+    fn deref(&self) -> &fs::File {
+        &self.inner
+    }
+}
+
+impl DerefMut for DurableFile {
+    fn deref_mut(&mut self) -> &mut fs::File {
+        &mut self.inner
+    }
+}
+
+impl Read for DurableFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.inner.read(buf)
+    }
+}
+
+impl Write for DurableFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.dirty |= written > 0;
+        Ok(written)
+    }
+
+    fn write_vectored(&mut self, bufs: &[io::IoSlice<'_>]) -> io::Result<usize> {
+        let written = self.inner.write_vectored(bufs)?;
+        self.dirty |= written > 0;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl Seek for DurableFile {
+    fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+        self.inner.seek(pos)
+    }
+}
+
+/// Syncs a dirty file with the configured durability level, then closes it
+impl Closable for DurableFile {
+    #[track_caller]
+    fn close(self) -> Result<(), CloseError> {
+        if self.dirty {
+            self.inner.close_with(self.durability)
+        } else {
+            self.inner.close()
+        }
+    }
+}
+
+/// Applies the given options regardless of the dirty state
+impl SyncClosable for DurableFile {
+    #[track_caller]
+    fn close_with_options(self, options: &CloseOptions) -> Result<(), CloseError> {
+        self.inner.close_with_options(options)
+    }
+}
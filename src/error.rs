@@ -0,0 +1,884 @@
+#[cfg(unix)]
+use std::os::fd::{BorrowedFd, IntoRawFd, OwnedFd, RawFd};
+
+#[cfg(windows)]
+use std::os::windows::io::{BorrowedHandle, IntoRawHandle, OwnedHandle, RawHandle};
+
+use std::any::{Any, TypeId};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::panic::Location;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+use std::time::SystemTime;
+
+/// A list specifying general categories of close errors
+///
+/// The categories are the same on all platforms, so they can be matched on without knowing the
+/// OS specific error codes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize),
+    serde(rename_all = "snake_case")
+)]
+#[non_exhaustive]
+pub enum CloseErrorKind {
+    /// The close operation was interrupted by a signal
+    Interrupted,
+    /// The device containing the file has no space left
+    OutOfSpace,
+    /// The user's disk quota has been exhausted
+    QuotaExceeded,
+    /// A low-level I/O error occurred, typically while writing back buffered data
+    IoFailure,
+    /// The file descriptor/handle was not valid
+    InvalidDescriptor,
+    /// Closing did not finish within the configured timeout
+    TimedOut,
+    /// The peer of a socket reset the connection, data sent before may not have been received
+    ConnectionReset,
+    /// The socket was not connected
+    NotConnected,
+    /// The closed data doesn't match what was written or expected, see CloseStage::Verify
+    VerificationFailed,
+    /// Any other error
+    Other,
+}
+
+/// Failures of network file systems like NFS and SMB that are reported at close time
+///
+/// Network file systems often only write buffered data back when the file is closed, so close is
+/// the first place where these errors show up.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize),
+    serde(rename_all = "snake_case")
+)]
+#[non_exhaustive]
+pub enum RemoteFailure {
+    /// The file was removed or replaced on the server (ESTALE, ERROR_FILE_INVALID)
+    StaleHandle,
+    /// The connection to the server was lost or timed out (e.g. ETIMEDOUT, ERROR_NETNAME_DELETED)
+    ConnectionLost,
+    /// Writing back buffered data failed (EIO, ERROR_LOST_WRITEBEHIND_DATA)
+    DelayedWriteFailed,
+}
+
+impl RemoteFailure {
+    /// Returns true if the file content on the server is likely incomplete or lost
+    pub fn is_data_loss_likely(self) -> bool {
+        matches!(
+            self,
+            RemoteFailure::StaleHandle | RemoteFailure::DelayedWriteFailed
+        )
+    }
+
+    /// Returns true if the failure is likely a transient network issue, so rewriting the file
+    /// later is worth a try
+    pub fn is_transient(self) -> bool {
+        self == RemoteFailure::ConnectionLost
+    }
+}
+
+/// The step of closing a file that failed
+///
+/// Recovery depends on the stage: after a failed flush the data is still in process memory, after
+/// a failed sync data may have been lost, after a failed close the file descriptor/handle is
+/// usually gone.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize),
+    serde(rename_all = "snake_case")
+)]
+#[non_exhaustive]
+pub enum CloseStage {
+    /// Writing buffered data to the file
+    Flush,
+    /// Syncing the file content to the storage device
+    Sync,
+    /// Closing the file descriptor/handle
+    Close,
+    /// Renaming the closed file into place
+    Rename,
+    /// Syncing the directory containing the file
+    SyncDir,
+    /// Removing a file that is not needed after closing, e.g. a temporary file
+    Remove,
+    /// Shutting down the write side of a socket
+    Shutdown,
+    /// Reading the remaining data of a socket until the peer closes its side
+    Drain,
+    /// Setting SO_LINGER of a socket
+    Linger,
+    /// Checking the closed data, e.g. against an expected digest
+    Verify,
+    /// Releasing an advisory lock on the file
+    Unlock,
+}
+
+/// What was done with the file after closing it failed, see FailurePolicy
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Cleanup {
+    /// The file was removed
+    Removed,
+    /// The file was renamed to the path
+    Renamed(PathBuf),
+    /// Cleaning up the file failed
+    Failed(io::Error),
+}
+
+/// A snapshot of the main properties of a CloseError
+///
+/// Unlike CloseError it can be cloned, e.g. to pass it to logging, metrics and a retry queue at
+/// the same time.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[non_exhaustive]
+pub struct CloseErrorInfo {
+    /// The category of the error
+    pub kind: CloseErrorKind,
+    /// The OS error code, if the error came from the OS
+    pub os_error: Option<i32>,
+    /// The path of the file, if it was attached to the error
+    pub path: Option<PathBuf>,
+    /// The step of closing the file that failed
+    pub stage: CloseStage,
+    /// When the error happened
+    pub timestamp: SystemTime,
+}
+
+/// Wraps any I/O error that can happen while closing a file
+///
+/// If the platform left the file open after the failed close, the error owns the file
+/// descriptor/handle, and closes it when dropped unless it is retried, leaked or otherwise taken
+/// back.
+pub struct CloseError {
+    io_error: io::Error,
+    stage: CloseStage,
+    path: Option<PathBuf>,
+    location: &'static Location<'static>,
+    timestamp: SystemTime,
+    // Rarely set details, boxed to keep the error small
+    details: Option<Box<Details>>,
+    #[cfg(unix)]
+    fd: Option<RawFd>,
+    #[cfg(unix)]
+    owned: Option<OwnedFd>,
+    // Kept as an integer, so CloseError is Send and Sync like OwnedHandle
+    #[cfg(windows)]
+    handle: Option<usize>,
+    #[cfg(windows)]
+    owned: Option<OwnedHandle>,
+    #[cfg(feature = "backtrace")]
+    backtrace: Box<std::backtrace::Backtrace>,
+    #[cfg(feature = "tracing-error")]
+    span_trace: Box<tracing_error::SpanTrace>,
+}
+
+#[derive(Debug, Default)]
+struct Details {
+    cleanup: Option<Cleanup>,
+    unwritten: Option<Vec<u8>>,
+    opened_at: Option<&'static Location<'static>>,
+    // In a Mutex, so CloseError is Sync without requiring it of the resource
+    retained: Option<Mutex<Box<dyn Any + Send>>>,
+}
+
+impl CloseError {
+    /// Creates an error that is not associated with a file descriptor/handle
+    #[track_caller]
+    pub(crate) fn detached(io_error: io::Error, stage: CloseStage) -> Self {
+        CloseError {
+            io_error,
+            stage,
+            path: None,
+            location: Location::caller(),
+            timestamp: SystemTime::now(),
+            details: None,
+            #[cfg(unix)]
+            fd: None,
+            #[cfg(windows)]
+            handle: None,
+            owned: None,
+            #[cfg(feature = "backtrace")]
+            backtrace: Box::new(std::backtrace::Backtrace::capture()),
+            #[cfg(feature = "tracing-error")]
+            span_trace: Box::new(tracing_error::SpanTrace::capture()),
+        }
+    }
+
+    #[cfg(unix)]
+    #[track_caller]
+    pub(crate) fn new(io_error: io::Error, fd: RawFd, owned: Option<OwnedFd>) -> Self {
+        let mut err = CloseError::detached(io_error, CloseStage::Close);
+        err.fd = Some(fd);
+        err.owned = owned;
+        err
+    }
+
+    #[cfg(windows)]
+    #[track_caller]
+    pub(crate) fn new(io_error: io::Error, handle: RawHandle, owned: Option<OwnedHandle>) -> Self {
+        let mut err = CloseError::detached(io_error, CloseStage::Close);
+        err.handle = Some(handle as usize);
+        err.owned = owned;
+        err
+    }
+
+    /// Creates an error for a step that failed before the file was closed
+    ///
+    /// The file is still open, so the error takes ownership of it.
+    #[track_caller]
+    pub(crate) fn unclosed(io_error: io::Error, file: fs::File, stage: CloseStage) -> Self {
+        #[cfg(unix)]
+        let owned = OwnedFd::from(file);
+        #[cfg(unix)]
+        let raw = std::os::fd::AsRawFd::as_raw_fd(&owned);
+        #[cfg(windows)]
+        let owned = OwnedHandle::from(file);
+        #[cfg(windows)]
+        let raw = std::os::windows::io::AsRawHandle::as_raw_handle(&owned);
+        let mut err = CloseError::new(io_error, raw, Some(owned));
+        err.stage = stage;
+        err
+    }
+
+    /// Creates an error for a step that failed before the resource was closed
+    ///
+    /// The resource is still open, so the error keeps it. A std::fs::File is owned like by
+    /// unclosed().
+    #[track_caller]
+    pub(crate) fn retained<T: Send + 'static>(
+        io_error: io::Error,
+        resource: T,
+        stage: CloseStage,
+    ) -> Self {
+        let resource: Box<dyn Any + Send> = Box::new(resource);
+        match resource.downcast::<fs::File>() {
+            Ok(file) => CloseError::unclosed(io_error, *file, stage),
+            Err(resource) => {
+                let mut err = CloseError::detached(io_error, stage);
+                err.details_mut().retained = Some(Mutex::new(resource));
+                err
+            }
+        }
+    }
+
+    /// Takes the still open file out of the error, so it is not kept open by the error
+    pub(crate) fn take_file(&mut self) -> Option<fs::File> {
+        self.owned.take().map(fs::File::from)
+    }
+
+    fn details_mut(&mut self) -> &mut Details {
+        self.details.get_or_insert_with(Default::default)
+    }
+
+    pub(crate) fn set_unwritten(&mut self, unwritten: Vec<u8>) {
+        self.details_mut().unwritten = Some(unwritten);
+    }
+
+    pub(crate) fn set_cleanup(&mut self, cleanup: Cleanup) {
+        self.details_mut().cleanup = Some(cleanup);
+    }
+
+    pub(crate) fn set_opened_at(&mut self, opened_at: &'static Location<'static>) {
+        self.details_mut().opened_at = Some(opened_at);
+    }
+
+    pub(crate) fn set_location(&mut self, location: &'static Location<'static>) {
+        self.location = location;
+    }
+
+    /// Returns the buffered data that could not be written, if a flush failed
+    ///
+    /// E.g. for a BufWriter, this is the content of the buffer that is not in the file.
+    pub fn unwritten(&self) -> Option<&[u8]> {
+        self.details.as_ref()?.unwritten.as_deref()
+    }
+
+    /// Takes the buffered data that could not be written out of the error
+    pub fn take_unwritten(&mut self) -> Option<Vec<u8>> {
+        self.details.as_mut()?.unwritten.take()
+    }
+
+    /// Takes the resource that was kept open after a failed step out of the error
+    ///
+    /// E.g. for a BufWriter whose flush failed, this is the inner writer, which was not closed.
+    /// Returns None if there is none or it is not a T. A std::fs::File can also be taken with
+    /// into_file().
+    pub fn take_retained<T: 'static>(&mut self) -> Option<T> {
+        if TypeId::of::<T>() == TypeId::of::<fs::File>() {
+            let file: Box<dyn Any> = Box::new(self.take_file()?);
+            return file.downcast().ok().map(|file| *file);
+        }
+        let details = self.details.as_mut()?;
+        let retained = details
+            .retained
+            .take()?
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner);
+        match retained.downcast() {
+            Ok(resource) => Some(*resource),
+            Err(retained) => {
+                details.retained = Some(Mutex::new(retained));
+                None
+            }
+        }
+    }
+
+    /// Returns what was done with the file after the failure, if a FailurePolicy applied
+    pub fn cleanup(&self) -> Option<&Cleanup> {
+        self.details.as_ref()?.cleanup.as_ref()
+    }
+
+    /// Returns where the file was opened, if it was opened through TrackedFile
+    pub fn opened_at(&self) -> Option<&'static Location<'static>> {
+        self.details.as_ref()?.opened_at
+    }
+
+    /// Returns the step of closing the file that failed
+    pub fn stage(&self) -> CloseStage {
+        self.stage
+    }
+
+    /// Returns the source code location of the close() call that failed
+    pub fn location(&self) -> &'static Location<'static> {
+        self.location
+    }
+
+    /// Attaches the path of the file to the error
+    pub fn with_path<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+
+    /// Returns the path of the file, if it was attached to the error
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Returns the backtrace captured when the error was created
+    ///
+    /// Whether a backtrace is actually captured depends on the RUST_BACKTRACE and
+    /// RUST_LIB_BACKTRACE environment variables, see std::backtrace::Backtrace::capture().
+    #[cfg(feature = "backtrace")]
+    pub fn backtrace(&self) -> &std::backtrace::Backtrace {
+        &self.backtrace
+    }
+
+    /// Returns the tracing spans that were active when the error was created
+    ///
+    /// Spans are only captured if the tracing subscriber has a tracing_error::ErrorLayer.
+    #[cfg(feature = "tracing-error")]
+    pub fn span_trace(&self) -> &tracing_error::SpanTrace {
+        &self.span_trace
+    }
+
+    /// Returns the file descriptor assigned to the file
+    ///
+    /// The value is only meant for reporting, the descriptor may already have been released and
+    /// reused by another file. Use as_fd() to access a descriptor that is still open. Errors of
+    /// steps that are not associated with a descriptor, e.g. renaming the file, return -1.
+    ///
+    /// OBS: This function is OS specific for unix
+    #[cfg(unix)]
+    pub fn raw_fd(&self) -> RawFd {
+        self.fd.unwrap_or(-1)
+    }
+
+    /// Borrows the file descriptor, if it is still open
+    ///
+    /// OBS: This function is OS specific for unix
+    #[cfg(unix)]
+    pub fn as_fd(&self) -> Option<BorrowedFd<'_>> {
+        use std::os::fd::AsFd;
+        self.owned.as_ref().map(|fd| fd.as_fd())
+    }
+
+    /// Consumes the error without closing the file descriptor, returning it if it is still open
+    ///
+    /// The caller becomes responsible for closing the descriptor.
+    ///
+    /// OBS: This function is OS specific for unix
+    #[cfg(unix)]
+    pub fn leak(self) -> Option<RawFd> {
+        self.owned.map(IntoRawFd::into_raw_fd)
+    }
+
+    /// Returns the handle assigned to the file
+    ///
+    /// The value is only meant for reporting, the handle may already have been released and reused
+    /// by another file. Use as_handle() to access a handle that is still open. Errors of steps that
+    /// are not associated with a handle, e.g. renaming the file, return INVALID_HANDLE_VALUE.
+    ///
+    /// OBS: This function is OS specific for windows systems
+    #[cfg(windows)]
+    pub fn raw_handle(&self) -> RawHandle {
+        self.handle.unwrap_or(usize::MAX) as RawHandle
+    }
+
+    /// Borrows the handle, if it is still open
+    ///
+    /// OBS: This function is OS specific for windows systems
+    #[cfg(windows)]
+    pub fn as_handle(&self) -> Option<BorrowedHandle<'_>> {
+        use std::os::windows::io::AsHandle;
+        self.owned.as_ref().map(|handle| handle.as_handle())
+    }
+
+    /// Consumes the error without closing the handle, returning it if it is still open
+    ///
+    /// The caller becomes responsible for closing the handle.
+    ///
+    /// OBS: This function is OS specific for windows systems
+    #[cfg(windows)]
+    pub fn leak(self) -> Option<RawHandle> {
+        self.owned.map(IntoRawHandle::into_raw_handle)
+    }
+
+    /// Returns a snapshot of the error that can be cloned
+    pub fn info(&self) -> CloseErrorInfo {
+        CloseErrorInfo {
+            kind: self.kind(),
+            os_error: self.io_error.raw_os_error(),
+            path: self.path.clone(),
+            stage: self.stage,
+            timestamp: self.timestamp,
+        }
+    }
+
+    /// Returns the error produced when the file was closed.
+    pub fn as_io_error(&self) -> &io::Error {
+        &self.io_error
+    }
+
+    /// Returns the category of the error
+    ///
+    /// For the errors of closing several resources, it is the category of the first error.
+    pub fn kind(&self) -> CloseErrorKind {
+        if let Some((_, first)) = self.errors().and_then(|errors| errors.errors().first()) {
+            return first.kind();
+        }
+        if self.stage == CloseStage::Verify && self.io_error.kind() == io::ErrorKind::InvalidData {
+            return CloseErrorKind::VerificationFailed;
+        }
+        match self.io_error.raw_os_error() {
+            Some(code) => crate::imp::kind(code),
+            None => match self.io_error.kind() {
+                io::ErrorKind::Interrupted => CloseErrorKind::Interrupted,
+                io::ErrorKind::TimedOut => CloseErrorKind::TimedOut,
+                _ => CloseErrorKind::Other,
+            },
+        }
+    }
+
+    /// Returns the OS error code, if the error came from the OS
+    pub fn raw_os_error(&self) -> Option<i32> {
+        self.io_error.raw_os_error()
+    }
+
+    /// Returns true if the device containing the file ran out of space
+    ///
+    /// This is ENOSPC on unix and ERROR_DISK_FULL or ERROR_HANDLE_DISK_FULL on windows.
+    pub fn is_out_of_space(&self) -> bool {
+        self.kind() == CloseErrorKind::OutOfSpace
+    }
+
+    /// Returns true if the user's disk quota was exceeded
+    ///
+    /// This is EDQUOT on unix and ERROR_DISK_QUOTA_EXCEEDED on windows.
+    pub fn is_quota_exceeded(&self) -> bool {
+        self.kind() == CloseErrorKind::QuotaExceeded
+    }
+
+    /// Returns true if a low-level I/O error occurred
+    ///
+    /// This is EIO on unix and e.g. ERROR_IO_DEVICE or ERROR_WRITE_FAULT on windows.
+    pub fn is_io_failure(&self) -> bool {
+        self.kind() == CloseErrorKind::IoFailure
+    }
+
+    /// Returns actionable guidance for the error, suitable for showing to a user
+    pub fn advice(&self) -> &'static str {
+        match self.kind() {
+            CloseErrorKind::Interrupted => {
+                "the close was interrupted by a signal; rewrite the file to make sure it is \
+                 complete"
+            }
+            CloseErrorKind::OutOfSpace => "free disk space and rewrite the file",
+            CloseErrorKind::QuotaExceeded => {
+                "free space within the disk quota or raise the quota, then rewrite the file"
+            }
+            CloseErrorKind::IoFailure => {
+                "the filesystem reported a writeback error; data may be lost, check the storage \
+                 device and rewrite the file"
+            }
+            CloseErrorKind::InvalidDescriptor => {
+                "the file descriptor/handle was not valid, which indicates a bug in the program"
+            }
+            CloseErrorKind::TimedOut => {
+                "closing the file took too long and continues in the background; the file may be \
+                 incomplete"
+            }
+            CloseErrorKind::ConnectionReset => {
+                "the peer reset the connection; data sent before may not have been received"
+            }
+            CloseErrorKind::NotConnected => {
+                "the socket was not connected, which indicates a bug or a lost connection"
+            }
+            CloseErrorKind::VerificationFailed => {
+                "the file doesn't contain what was written or expected; the transfer may have been \
+                 truncated or the storage may be faulty, rewrite the file"
+            }
+            CloseErrorKind::Other => "the file may be incomplete; rewrite it",
+        }
+    }
+
+    /// Returns true if closing the file again may succeed
+    ///
+    /// This follows the documented close semantics of the platform: Linux, macOS and the BSDs
+    /// release the file descriptor even when close() fails, so retrying is never correct there and
+    /// could close an unrelated file that reused the descriptor. AIX keeps the descriptor open when
+    /// close() is interrupted by a signal.
+    pub fn is_retryable(&self) -> bool {
+        self.owned.is_some()
+    }
+
+    /// Returns true if data written to the file may not have reached the storage
+    ///
+    /// Network file systems like NFS only write back buffered data when the file is closed, so
+    /// errors like ENOSPC or EIO returned by close() indicate that the file content is incomplete.
+    /// The file should be rewritten.
+    pub fn is_data_loss(&self) -> bool {
+        matches!(
+            self.kind(),
+            CloseErrorKind::OutOfSpace | CloseErrorKind::QuotaExceeded | CloseErrorKind::IoFailure
+        ) || self
+            .remote_failure()
+            .is_some_and(RemoteFailure::is_data_loss_likely)
+    }
+
+    /// Classifies the error as a failure of a network file system, if it is one
+    ///
+    /// EIO is reported as RemoteFailure::DelayedWriteFailed, as close only writes data back on
+    /// network file systems, but the same code is returned for local disk failures e.g. on
+    /// FUSE file systems.
+    pub fn remote_failure(&self) -> Option<RemoteFailure> {
+        self.io_error
+            .raw_os_error()
+            .and_then(crate::imp::remote_failure)
+    }
+
+    /// Closes the file again if the platform left it open after the failed close
+    ///
+    /// If the error is not retryable (see is_retryable()), no system call is made and the error is
+    /// returned unchanged, so it is safe to call this in a loop:
+    ///
+    /// ```no_run
+    /// # use close_file::Closable;
+    /// # let f = std::fs::File::open("temp").unwrap();
+    /// let mut result = f.close();
+    /// while let Err(e) = result {
+    ///     if !e.is_retryable() {
+    ///         // give up and rewrite the file
+    ///         break;
+    ///     }
+    ///     result = e.retry();
+    /// }
+    /// ```
+    #[track_caller]
+    pub fn retry(mut self) -> Result<(), CloseError> {
+        match self.owned.take() {
+            Some(owned) => crate::imp::close(owned),
+            None => Err(self),
+        }
+    }
+
+    /// Consumes the error, returning the file if it is still open
+    ///
+    /// The file can then be rewritten, e.g. after seeking to the start, and closed again. Returns
+    /// None if the failed close already released the file descriptor/handle, which is the case for
+    /// most errors reported by the OS close call itself.
+    pub fn into_file(self) -> Option<fs::File> {
+        self.owned.map(fs::File::from)
+    }
+
+    /// Consumes the error, returning the error produced when the file was closed.
+    ///
+    /// The file descriptor/handle is discarded. Use `io::Error::from` instead to keep it.
+    pub fn into_io_error(self) -> io::Error {
+        self.io_error
+    }
+
+    /// Creates an error from the errors of closing several resources
+    ///
+    /// The stage is the stage of the first error.
+    #[track_caller]
+    pub(crate) fn from_errors(errors: CloseErrors) -> Self {
+        let (kind, stage) = match errors.errors.first() {
+            Some((_, first)) => (first.io_error.kind(), first.stage),
+            None => (io::ErrorKind::Other, CloseStage::Close),
+        };
+        CloseError::detached(io::Error::new(kind, errors), stage)
+    }
+
+    /// Returns the individual errors, if closing several resources failed, e.g. the elements of a
+    /// Vec
+    pub fn errors(&self) -> Option<&CloseErrors> {
+        self.io_error.get_ref()?.downcast_ref()
+    }
+
+    /// Consumes the error, returning the individual errors if closing several resources failed
+    pub fn into_errors(self) -> Result<CloseErrors, CloseError> {
+        if self.errors().is_none() {
+            return Err(self);
+        }
+        match self.io_error.into_inner().map(|inner| inner.downcast()) {
+            Some(Ok(errors)) => Ok(*errors),
+            _ => unreachable!("the payload was checked to be CloseErrors"),
+        }
+    }
+}
+
+/// The errors of closing several resources, e.g. the elements of a Vec
+///
+/// Every resource is closed even if closing an earlier one failed. The errors are in the order of
+/// the resources, each with the index of its resource.
+#[derive(Debug, Default)]
+pub struct CloseErrors {
+    errors: Vec<(usize, CloseError)>,
+}
+
+impl CloseErrors {
+    pub(crate) fn new() -> Self {
+        CloseErrors::default()
+    }
+
+    pub(crate) fn push(&mut self, index: usize, err: CloseError) {
+        self.errors.push((index, err));
+    }
+
+    /// Returns Ok if no error was pushed
+    pub(crate) fn into_result(self) -> Result<(), CloseErrors> {
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(self)
+        }
+    }
+
+    /// Returns the errors with the indices of their resources
+    pub fn errors(&self) -> &[(usize, CloseError)] {
+        &self.errors
+    }
+
+    /// Returns the number of resources that failed to close
+    pub fn len(&self) -> usize {
+        self.errors.len()
+    }
+
+    /// Returns true if there are no errors
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// Returns the indices of the resources that failed to close
+    pub fn indices(&self) -> impl Iterator<Item = usize> + '_ {
+        self.errors.iter().map(|(index, _)| *index)
+    }
+
+    /// Consumes the errors, returning them with the indices of their resources
+    pub fn into_vec(self) -> Vec<(usize, CloseError)> {
+        self.errors
+    }
+}
+
+impl IntoIterator for CloseErrors {
+    type Item = (usize, CloseError);
+    type IntoIter = std::vec::IntoIter<(usize, CloseError)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.errors.into_iter()
+    }
+}
+
+impl std::error::Error for CloseErrors {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self.errors.first() {
+            Some((_, first)) => Some(first),
+            None => None,
+        }
+    }
+}
+
+/// Formats the number of errors and the first error, e.g. "2 of the closes failed, the first at
+/// index 3: failed to close fd 7: Input/output error (os error 5)"
+impl fmt::Display for CloseErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.errors.first() {
+            Some((index, first)) => write!(
+                f,
+                "{} of the closes failed, the first at index {}: {}",
+                self.errors.len(),
+                index,
+                first
+            ),
+            None => write!(f, "no close failed"),
+        }
+    }
+}
+
+impl From<CloseErrors> for io::Error {
+    fn from(errors: CloseErrors) -> io::Error {
+        let kind = match errors.errors.first() {
+            Some((_, first)) => first.io_error.kind(),
+            None => io::ErrorKind::Other,
+        };
+        io::Error::new(kind, errors)
+    }
+}
+
+/// The I/O error is part of the Display output, so it is not returned by source() as well, which
+/// would print it twice in error chains. Use as_io_error() to access it.
+impl std::error::Error for CloseError {}
+
+/// Serializes the error as a map with the fields `kind`, `stage`, `os_error`, `message`, `fd`
+/// (unix) or `handle` (windows) and `path`
+///
+/// `os_error`, `fd`/`handle` and `path` are null when unknown, the path is converted lossily to
+/// UTF-8.
+#[cfg(feature = "serde")]
+impl serde::Serialize for CloseError {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        let mut s = serializer.serialize_struct("CloseError", 6)?;
+        s.serialize_field("kind", &self.kind())?;
+        s.serialize_field("stage", &self.stage)?;
+        s.serialize_field("os_error", &self.io_error.raw_os_error())?;
+        s.serialize_field("message", &self.io_error.to_string())?;
+        #[cfg(unix)]
+        s.serialize_field("fd", &self.fd)?;
+        #[cfg(windows)]
+        s.serialize_field("handle", &self.handle)?;
+        s.serialize_field(
+            "path",
+            &self.path.as_ref().map(|path| path.to_string_lossy()),
+        )?;
+        s.end()
+    }
+}
+
+/// Reports the category as error code (e.g. `close_file::out_of_space`) and the advice as help
+#[cfg(feature = "miette")]
+impl miette::Diagnostic for CloseError {
+    fn code<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        let code = match self.kind() {
+            CloseErrorKind::Interrupted => "close_file::interrupted",
+            CloseErrorKind::OutOfSpace => "close_file::out_of_space",
+            CloseErrorKind::QuotaExceeded => "close_file::quota_exceeded",
+            CloseErrorKind::IoFailure => "close_file::io_failure",
+            CloseErrorKind::InvalidDescriptor => "close_file::invalid_descriptor",
+            CloseErrorKind::TimedOut => "close_file::timed_out",
+            CloseErrorKind::ConnectionReset => "close_file::connection_reset",
+            CloseErrorKind::NotConnected => "close_file::not_connected",
+            CloseErrorKind::VerificationFailed => "close_file::verification_failed",
+            CloseErrorKind::Other => "close_file::other",
+        };
+        Some(Box::new(code))
+    }
+
+    fn help<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        Some(Box::new(self.advice()))
+    }
+}
+
+/// Returns the context message used by the anyhow and eyre integrations
+#[cfg(any(feature = "anyhow", feature = "eyre"))]
+pub(crate) fn context_message(path: &Path, stage: CloseStage) -> String {
+    match stage {
+        CloseStage::Close => format!("failed to close {}", path.display()),
+        stage => format!("failed to close {} during {}", path.display(), stage),
+    }
+}
+
+/// Wraps the CloseError into an io::Error of the same kind
+///
+/// The CloseError is kept as the custom error payload, so the file descriptor can be recovered
+/// with `io::Error::get_ref` or `io::Error::into_inner` and a downcast to CloseError.
+impl From<CloseError> for io::Error {
+    fn from(err: CloseError) -> io::Error {
+        io::Error::new(err.io_error.kind(), err)
+    }
+}
+
+/// Formats the error as a diagnostic line
+///
+/// E.g. "failed to close /var/data/out.tmp (fd 12) during sync: No space left on device (os error
+/// 28)". If a FailurePolicy cleaned up the file, the outcome is appended. The alternate format
+/// (`{:#}`) is compact and only prefixes the I/O error with the path, if known.
+impl fmt::Display for CloseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            if let Some(path) = &self.path {
+                write!(f, "{}: ", path.display())?;
+            }
+            return fmt::Display::fmt(&self.io_error, f);
+        }
+
+        #[cfg(unix)]
+        let descriptor = self.fd.map(|fd| format!("fd {}", fd));
+        #[cfg(windows)]
+        let descriptor = self.handle.map(|handle| format!("handle {:#x}", handle));
+        match (&self.path, descriptor) {
+            (Some(path), Some(descriptor)) => {
+                write!(f, "failed to close {} ({})", path.display(), descriptor)?
+            }
+            (Some(path), None) => write!(f, "failed to close {}", path.display())?,
+            (None, Some(descriptor)) => write!(f, "failed to close {}", descriptor)?,
+            (None, None) => write!(f, "failed to close file")?,
+        }
+        if self.stage != CloseStage::Close {
+            write!(f, " during {}", self.stage)?;
+        }
+        write!(f, ": {}", self.io_error)?;
+        if let Some(opened_at) = self.opened_at() {
+            write!(f, "; opened at {}", opened_at)?;
+        }
+        match self.cleanup() {
+            Some(Cleanup::Removed) => write!(f, "; the file was removed"),
+            Some(Cleanup::Renamed(path)) => write!(f, "; the file was kept as {}", path.display()),
+            Some(Cleanup::Failed(e)) => write!(f, "; cleaning up the file failed: {}", e),
+            None => Ok(()),
+        }
+    }
+}
+
+impl fmt::Display for CloseStage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            CloseStage::Flush => "flush",
+            CloseStage::Sync => "sync",
+            CloseStage::Close => "close",
+            CloseStage::Rename => "rename",
+            CloseStage::SyncDir => "directory sync",
+            CloseStage::Remove => "remove",
+            CloseStage::Shutdown => "shutdown",
+            CloseStage::Drain => "drain",
+            CloseStage::Linger => "linger",
+            CloseStage::Verify => "verify",
+            CloseStage::Unlock => "unlock",
+        })
+    }
+}
+
+impl fmt::Debug for CloseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.io_error, f)
+    }
+}
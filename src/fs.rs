@@ -0,0 +1,321 @@
+//! A facade of std::fs that reports errors when closing files
+//!
+//! The functions and types mirror std::fs, so the module can replace it with
+//! `use close_file::fs;`. Files are closed with close() and close errors are returned as
+//! io::Error with the CloseError as inner error. Everything that doesn't involve closing files is
+//! re-exported from std::fs.
+//!
+//! ```
+//! use close_file::fs;
+//! use close_file::Closable;
+//! use std::io::Write;
+//!
+//! let path = std::env::temp_dir().join("close-file-fs-doc");
+//! let mut f = fs::File::create(&path).unwrap();
+//! f.write_all(b"Hello, world!").unwrap();
+//! f.close().unwrap();
+//! assert_eq!(fs::read(&path).unwrap(), b"Hello, world!");
+//! fs::remove_file(&path).unwrap();
+//! ```
+use crate::{
+    imp, Closable, CloseError, CloseOptions, Durability, FailurePolicy, SyncClosable, TryClosable,
+    TryCloseError,
+};
+use std::io::{self, Read, Seek, Write};
+use std::ops::{Deref, DerefMut};
+use std::path::{Path, PathBuf};
+
+pub use std::fs::{
+    canonicalize, create_dir, create_dir_all, hard_link, metadata, read_dir, read_link, remove_dir,
+    remove_dir_all, remove_file, rename, set_permissions, symlink_metadata, DirBuilder, DirEntry,
+    FileType, Metadata, OpenOptions, Permissions, ReadDir,
+};
+
+/// A file that knows its path and reports errors when closing
+///
+/// Dereferences to std::fs::File for everything else. Close errors carry the path of the file.
+#[derive(Debug)]
+pub struct File {
+    inner: std::fs::File,
+    path: PathBuf,
+    on_failure: FailurePolicy,
+}
+
+impl File {
+    /// Opens a file in read-only mode, like std::fs::File::open()
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<File> {
+        File::open_with(path, OpenOptions::new().read(true))
+    }
+
+    /// Opens a file in write-only mode, like std::fs::File::create()
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<File> {
+        File::open_with(
+            path,
+            OpenOptions::new().write(true).create(true).truncate(true),
+        )
+    }
+
+    /// Opens a file with the given options
+    pub fn open_with<P: AsRef<Path>>(path: P, options: &OpenOptions) -> io::Result<File> {
+        let path = path.as_ref();
+        Ok(File {
+            inner: options.open(path)?,
+            path: path.to_path_buf(),
+            on_failure: FailurePolicy::Keep,
+        })
+    }
+
+    /// The path the file was opened with
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Sets what to do with the file when closing it failed, see FailurePolicy
+    pub fn set_failure_policy(&mut self, policy: FailurePolicy) {
+        self.on_failure = policy;
+    }
+
+    /// Converts the file into a std::fs::File, which doesn't report close errors when dropped
+    pub fn into_inner(self) -> std::fs::File {
+        self.inner
+    }
+
+    pub(crate) fn into_parts(self) -> (std::fs::File, PathBuf, FailurePolicy) {
+        (self.inner, self.path, self.on_failure)
+    }
+}
+
+impl Deref for File {
+    type Target = std::fs::File;
+
+    fn deref(&self) -> &std::fs::File {
+        &self.inner
+    }
+}
+
+impl DerefMut for File {
+    fn deref_mut(&mut self) -> &mut std::fs::File {
+        &mut self.inner
+    }
+}
+
+impl Read for File {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.inner.read(buf)
+    }
+
+    fn read_to_end(&mut self, buf: &mut Vec<u8>) -> io::Result<usize> {
+        self.inner.read_to_end(buf)
+    }
+}
+
+impl Write for File {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.inner.write(buf)
+    }
+
+    fn write_vectored(&mut self, bufs: &[io::IoSlice<'_>]) -> io::Result<usize> {
+        self.inner.write_vectored(bufs)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl Seek for File {
+    fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+        self.inner.seek(pos)
+    }
+}
+
+impl Closable for File {
+    #[track_caller]
+    fn close(self) -> Result<(), CloseError> {
+        let on_failure = self.on_failure;
+        self.inner.close_with_path(self.path).map_err(|mut e| {
+            on_failure.apply(&mut e);
+            e
+        })
+    }
+}
+
+impl SyncClosable for File {
+    #[track_caller]
+    fn close_with_options(self, options: &CloseOptions) -> Result<(), CloseError> {
+        let (path, on_failure) = (self.path, self.on_failure);
+        self.inner.close_with_options(options).map_err(|e| {
+            let mut e = e.with_path(path);
+            on_failure.apply(&mut e);
+            e
+        })
+    }
+}
+
+/// Hands the file back with its path and policy, the policy is only applied if the file was
+/// released
+impl TryClosable for File {
+    #[track_caller]
+    fn try_close(self) -> Result<(), TryCloseError<Self>> {
+        let (path, on_failure) = (self.path, self.on_failure);
+        match self.inner.try_close() {
+            Ok(()) => Ok(()),
+            Err(TryCloseError::Retained(inner, e)) => Err(TryCloseError::Retained(
+                File {
+                    inner,
+                    path,
+                    on_failure,
+                },
+                e,
+            )),
+            Err(TryCloseError::Closed(e)) => {
+                let mut e = e.with_path(path);
+                on_failure.apply(&mut e);
+                Err(TryCloseError::Closed(e))
+            }
+        }
+    }
+}
+
+/// Options for opening files with write-through semantics, mirroring std::fs::OpenOptions
+///
+/// With write-through, each write only returns once the data is on the storage device, so write
+/// errors surface at the write instead of when syncing or closing, at the cost of slower writes.
+///
+/// | Level                   | unix     | windows                  |
+/// |-------------------------|----------|--------------------------|
+/// | None                    | -        | -                        |
+/// | DataSync                | O_DSYNC  | FILE_FLAG_WRITE_THROUGH  |
+/// | Sync, Barrier, FullSync | O_SYNC   | FILE_FLAG_WRITE_THROUGH  |
+///
+/// On Apple platforms O_SYNC doesn't flush the drive cache, close the file with close_synced() to
+/// be safe against power failure.
+///
+/// ```
+/// use close_file::{Closable, Durability};
+/// use close_file::fs::DurableOpenOptions;
+/// use std::io::Write;
+///
+/// let path = std::env::temp_dir().join("close-file-durable-open-doc");
+/// let mut f = DurableOpenOptions::new()
+///     .write(true)
+///     .create(true)
+///     .write_through(Durability::DataSync)
+///     .open(&path)
+///     .unwrap();
+/// f.write_all(b"Hello, world!").unwrap();
+/// f.close().unwrap();
+/// # std::fs::remove_file(&path).unwrap();
+/// ```
+#[derive(Clone, Debug)]
+pub struct DurableOpenOptions {
+    options: OpenOptions,
+    write_through: Durability,
+}
+
+impl DurableOpenOptions {
+    /// Creates options with everything disabled, like OpenOptions::new()
+    pub fn new() -> Self {
+        DurableOpenOptions {
+            options: OpenOptions::new(),
+            write_through: Durability::None,
+        }
+    }
+
+    /// Sets the option for read access, see OpenOptions::read()
+    pub fn read(&mut self, read: bool) -> &mut Self {
+        self.options.read(read);
+        self
+    }
+
+    /// Sets the option for write access, see OpenOptions::write()
+    pub fn write(&mut self, write: bool) -> &mut Self {
+        self.options.write(write);
+        self
+    }
+
+    /// Sets the option for append mode, see OpenOptions::append()
+    pub fn append(&mut self, append: bool) -> &mut Self {
+        self.options.append(append);
+        self
+    }
+
+    /// Sets the option for truncating the file, see OpenOptions::truncate()
+    pub fn truncate(&mut self, truncate: bool) -> &mut Self {
+        self.options.truncate(truncate);
+        self
+    }
+
+    /// Sets the option to create the file if it doesn't exist, see OpenOptions::create()
+    pub fn create(&mut self, create: bool) -> &mut Self {
+        self.options.create(create);
+        self
+    }
+
+    /// Sets the option to always create a new file, see OpenOptions::create_new()
+    pub fn create_new(&mut self, create_new: bool) -> &mut Self {
+        self.options.create_new(create_new);
+        self
+    }
+
+    /// Sets how durable each write is before it returns
+    pub fn write_through(&mut self, durability: Durability) -> &mut Self {
+        self.write_through = durability;
+        self
+    }
+
+    /// Opens the file at the given path with the options
+    pub fn open<P: AsRef<Path>>(&self, path: P) -> io::Result<File> {
+        let mut options = self.options.clone();
+        imp::set_write_through(&mut options, self.write_through);
+        File::open_with(path, &options)
+    }
+}
+
+impl Default for DurableOpenOptions {
+    fn default() -> Self {
+        DurableOpenOptions::new()
+    }
+}
+
+/// Reads the whole file, like std::fs::read()
+#[track_caller]
+pub fn read<P: AsRef<Path>>(path: P) -> io::Result<Vec<u8>> {
+    let mut file = File::open(path)?;
+    let mut contents = Vec::new();
+    file.read_to_end(&mut contents)?;
+    file.close()?;
+    Ok(contents)
+}
+
+/// Reads the whole file into a string, like std::fs::read_to_string()
+#[track_caller]
+pub fn read_to_string<P: AsRef<Path>>(path: P) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut contents = String::new();
+    file.read_to_string(&mut contents)?;
+    file.close()?;
+    Ok(contents)
+}
+
+/// Writes the contents to the file, like std::fs::write()
+#[track_caller]
+pub fn write<P: AsRef<Path>, C: AsRef<[u8]>>(path: P, contents: C) -> io::Result<()> {
+    crate::write_and_close(path, contents)?;
+    Ok(())
+}
+
+/// Copies the content and permissions of a file, like std::fs::copy()
+///
+/// Returns the number of bytes copied. Both files are closed, errors closing either of them are
+/// returned, the destination is closed first.
+#[track_caller]
+pub fn copy<P: AsRef<Path>, Q: AsRef<Path>>(from: P, to: Q) -> io::Result<u64> {
+    let mut from = File::open(from)?;
+    let mut to = File::create(to)?;
+    to.set_permissions(from.metadata()?.permissions())?;
+    let copied = io::copy(&mut from.inner, &mut to.inner)?;
+    to.close()?;
+    from.close()?;
+    Ok(copied)
+}
@@ -0,0 +1,93 @@
+use crate::{Closable, CloseError};
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+use std::sync::mpsc::Sender;
+
+enum Delivery {
+    Callback(Box<dyn FnOnce(Result<(), CloseError>) + Send>),
+    Sender(Sender<Result<(), CloseError>>),
+}
+
+/// Closes the value when the guard is dropped and delivers the result
+///
+/// Suits long-lived tasks like actors whose teardown path can't return a Result. The result is
+/// passed to a callback or sent through a channel, a disconnected channel is ignored.
+///
+/// ```
+/// use close_file::CloseGuard;
+/// use std::io::Write;
+///
+/// let (sender, receiver) = std::sync::mpsc::channel();
+/// {
+///     let mut f = CloseGuard::with_sender(std::fs::File::create("temp").unwrap(), sender);
+///     f.write_all(b"Hello, world!").unwrap();
+/// }
+/// receiver.recv().unwrap().unwrap();
+/// ```
+pub struct CloseGuard<T: Closable> {
+    // Only None after into_inner()
+    inner: Option<T>,
+    delivery: Option<Delivery>,
+}
+
+impl<T: Closable> CloseGuard<T> {
+    /// Creates a guard that passes the result to the callback
+    pub fn with_callback<F: FnOnce(Result<(), CloseError>) + Send + 'static>(
+        inner: T,
+        callback: F,
+    ) -> Self {
+        CloseGuard {
+            inner: Some(inner),
+            delivery: Some(Delivery::Callback(Box::new(callback))),
+        }
+    }
+
+    /// Creates a guard that sends the result through the channel
+    pub fn with_sender(inner: T, sender: Sender<Result<(), CloseError>>) -> Self {
+        CloseGuard {
+            inner: Some(inner),
+            delivery: Some(Delivery::Sender(sender)),
+        }
+    }
+
+    /// Returns the value without closing it, nothing is delivered
+    pub fn into_inner(mut self) -> T {
+        self.inner.take().expect("the value is only taken once")
+    }
+}
+
+impl<T: Closable> Deref for CloseGuard<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.inner.as_ref().expect("the value is only taken once")
+    }
+}
+
+impl<T: Closable> DerefMut for CloseGuard<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.inner.as_mut().expect("the value is only taken once")
+    }
+}
+
+impl<T: Closable + fmt::Debug> fmt::Debug for CloseGuard<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CloseGuard")
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
+impl<T: Closable> Drop for CloseGuard<T> {
+    fn drop(&mut self) {
+        if let (Some(inner), Some(delivery)) = (self.inner.take(), self.delivery.take()) {
+            let result = inner.close();
+            match delivery {
+                Delivery::Callback(callback) => callback(result),
+                Delivery::Sender(sender) => {
+                    let _ = sender.send(result);
+                }
+            }
+        }
+    }
+}
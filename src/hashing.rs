@@ -0,0 +1,115 @@
+use crate::{Closable, CloseError, CloseStage};
+use digest::{Digest, Output};
+use std::fmt;
+use std::io::{self, Write};
+
+/// A writer that hashes everything written through it and returns the digest on close
+///
+/// The hash is any digest::Digest, e.g. sha2::Sha256. With expect() set, closing fails with the
+/// stage CloseStage::Verify if the digest of the written data differs, after the writer was
+/// closed.
+///
+/// ```
+/// use close_file::HashingWriter;
+/// use sha2::{Digest, Sha256};
+/// use std::io::Write;
+///
+/// let f = std::fs::File::create("temp").unwrap();
+/// let mut w = HashingWriter::<_, Sha256>::new(f);
+/// w.write_all(b"Hello, world!").unwrap();
+/// let digest = w.close_hashed().unwrap();
+/// assert_eq!(digest, Sha256::digest(b"Hello, world!"));
+/// ```
+pub struct HashingWriter<W, D: Digest> {
+    inner: W,
+    hasher: D,
+    expected: Option<Output<D>>,
+}
+
+impl<W, D: Digest> HashingWriter<W, D> {
+    /// Wraps a writer with a new hasher
+    pub fn new(inner: W) -> Self {
+        HashingWriter::with_hasher(inner, D::new())
+    }
+
+    /// Wraps a writer with a hasher that may already have hashed other data
+    pub fn with_hasher(inner: W, hasher: D) -> Self {
+        HashingWriter {
+            inner,
+            hasher,
+            expected: None,
+        }
+    }
+
+    /// Sets the digest the written data must have when the writer is closed
+    pub fn expect(mut self, digest: Output<D>) -> Self {
+        self.expected = Some(digest);
+        self
+    }
+
+    /// Closes the writer and returns the digest of the written data
+    ///
+    /// Errors closing the writer are returned first, the digest is only verified after a
+    /// successful close.
+    #[track_caller]
+    pub fn close_hashed(self) -> Result<Output<D>, CloseError>
+    where
+        W: Closable,
+    {
+        self.inner.close()?;
+        let digest = self.hasher.finalize();
+        match self.expected {
+            Some(expected) if expected != digest => Err(CloseError::detached(
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "digest mismatch, expected {}, got {}",
+                        hex(&expected),
+                        hex(&digest)
+                    ),
+                ),
+                CloseStage::Verify,
+            )),
+            _ => Ok(digest),
+        }
+    }
+
+    /// Returns the writer and the digest of the data written so far, without closing or verifying
+    pub fn into_parts(self) -> (W, Output<D>) {
+        (self.inner, self.hasher.finalize())
+    }
+}
+
+impl<W: Write, D: Digest> Write for HashingWriter<W, D> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.hasher.update(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Closes the writer and verifies the digest, if one is expected
+impl<W: Closable, D: Digest> Closable for HashingWriter<W, D> {
+    #[track_caller]
+    fn close(self) -> Result<(), CloseError> {
+        self.close_hashed()?;
+        Ok(())
+    }
+}
+
+impl<W: fmt::Debug, D: Digest> fmt::Debug for HashingWriter<W, D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HashingWriter")
+            .field("inner", &self.inner)
+            .field("expected", &self.expected)
+            .finish()
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
@@ -0,0 +1,91 @@
+use crate::CloseError;
+use std::fmt;
+use std::sync::RwLock;
+
+static HANDLER: RwLock<Option<fn(DropCloseError)>> = RwLock::new(None);
+
+/// A close error that happened when a value was dropped instead of being closed
+///
+/// Wrappers like TrackedFile and AtomicWriteFile close or clean up in Drop as a fallback, where
+/// errors can't be returned. They are passed to the handler set with
+/// set_drop_close_error_handler(). MustClose with DropAction::Log reports being dropped as an error
+/// of the kind CloseErrorKind::Other, or prints it to stderr if no handler is set.
+#[derive(Debug)]
+pub struct DropCloseError {
+    error: CloseError,
+    type_name: &'static str,
+    unwinding: bool,
+}
+
+impl DropCloseError {
+    /// Returns the close error
+    pub fn error(&self) -> &CloseError {
+        &self.error
+    }
+
+    /// Consumes the error, returning the close error
+    pub fn into_error(self) -> CloseError {
+        self.error
+    }
+
+    /// Returns the name of the type of the dropped value
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+
+    /// Returns true if the value was dropped while the thread was panicking
+    pub fn during_unwinding(&self) -> bool {
+        self.unwinding
+    }
+}
+
+/// Formats the error, e.g. "error dropping close_file::TrackedFile: failed to close out.txt (fd 3):
+/// Input/output error (os error 5)"
+impl fmt::Display for DropCloseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "error dropping {}: {}", self.type_name, self.error)
+    }
+}
+
+impl std::error::Error for DropCloseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// Sets the handler for close errors of values that were dropped instead of being closed
+///
+/// Without a handler these errors are discarded, except for MustClose with DropAction::Log, which
+/// prints to stderr. The handler is called on the dropping thread, also during unwinding, so it
+/// must not panic.
+///
+/// ```
+/// close_file::set_drop_close_error_handler(|e| eprintln!("{}", e));
+/// ```
+pub fn set_drop_close_error_handler(handler: fn(DropCloseError)) {
+    *HANDLER
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(handler);
+}
+
+/// Returns true if a handler was set with set_drop_close_error_handler()
+pub(crate) fn has_handler() -> bool {
+    HANDLER
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .is_some()
+}
+
+/// Passes the error of dropping a value of type T to the handler
+pub(crate) fn report_drop_error<T: ?Sized>(error: CloseError) {
+    let handler = *HANDLER
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    if let Some(handler) = handler {
+        handler(DropCloseError {
+            error,
+            type_name: std::any::type_name::<T>(),
+            unwinding: std::thread::panicking(),
+        });
+    }
+}
@@ -21,107 +21,258 @@
 //! underlying I/O error and the file descriptor/handle of the file is returned. Depending on your
 //! system and the error, closing the file may be retried, but in most cases the best solution is
 //! to try to rewrite the file.
+//!
+//! The minimum supported Rust version is 1.87, which added std::io::pipe().
+#[cfg(feature = "anyhow")]
+pub mod anyhow;
+mod async_close;
+#[cfg(feature = "async-fs")]
+pub mod async_fs;
+#[cfg(feature = "async-std")]
+pub mod async_std;
+mod atomic;
+mod batch;
+mod buffered;
+mod closer;
+mod collections;
+mod dir;
+mod durable;
+mod error;
+#[cfg(feature = "eyre")]
+pub mod eyre;
+pub mod fs;
+mod guard;
+#[cfg(feature = "digest")]
+mod hashing;
+mod hook;
 #[cfg(unix)]
-use std::os::fd::RawFd;
-
+#[path = "unix.rs"]
+mod imp;
 #[cfg(windows)]
-use std::os::windows::io::RawHandle;
-
-use std::fmt;
-use std::io;
-
-/// Wraps any I/O error that can happen while closing a file
-pub struct CloseError {
-    io_error: io::Error,
-    #[cfg(unix)]
-    fd: RawFd,
-    #[cfg(windows)]
-    handle: RawHandle,
-}
+#[path = "windows.rs"]
+mod imp;
+mod lock;
+mod must_close;
+mod net;
+mod policy;
+mod probe;
+mod process;
+#[cfg(feature = "raw")]
+pub mod raw;
+mod scope;
+mod sequence;
+mod shared;
+mod stdio;
+mod strict;
+mod sync;
+mod syncing;
+mod token;
+#[cfg(feature = "tokio")]
+pub mod tokio;
+mod tracked;
+mod transaction;
+mod try_close;
+mod verify;
+mod witness;
+mod write;
 
-impl CloseError {
-    /// Returns the file descriptor assigned to the file
-    ///
-    /// This should only be used in very rare cases. Check you OS documentation before use.
-    ///
-    /// OBS: This function is OS specific for unix
-    #[cfg(unix)]
-    pub fn raw_fd(&self) -> RawFd {
-        self.fd
-    }
+pub use crate::async_close::AsyncClosable;
+pub use crate::atomic::{write_atomic, AtomicWriteFile, AtomicWriteOptions};
+pub use crate::batch::close_all_synced;
+pub use crate::closer::Closer;
+pub use crate::collections::{close_all, close_all_fail_fast, FileSet};
+pub use crate::dir::{rename_durable, sync_parent_dir, Dir};
+pub use crate::durable::DurableFile;
+pub use crate::error::{
+    Cleanup, CloseError, CloseErrorInfo, CloseErrorKind, CloseErrors, CloseStage, RemoteFailure,
+};
+pub use crate::guard::CloseGuard;
+#[cfg(feature = "digest")]
+pub use crate::hashing::HashingWriter;
+pub use crate::hook::{set_drop_close_error_handler, DropCloseError};
+pub use crate::lock::LockedFile;
+pub use crate::must_close::{DropAction, MustClose};
+#[cfg(unix)]
+pub use crate::net::close_and_unlink;
+pub use crate::net::{graceful_close, GracefulClose};
+pub use crate::policy::FailurePolicy;
+pub use crate::probe::{probe_durability, Confidence, DurabilityProbe, FileSystem};
+pub use crate::process::{ChildExt, ChildTeardown};
+pub use crate::scope::{scope, CloseScope};
+pub use crate::sequence::{CloseSequence, SequenceError};
+pub use crate::shared::{close_arc, release_arc, CloseArcError};
+pub use crate::stdio::{close_stderr, close_stdout, run, StdioExit};
+pub use crate::strict::{is_strict, set_strict};
+pub use crate::sync::{start_writeback, wait_writeback, CloseOptions, Durability, SyncClosable};
+pub use crate::syncing::{SyncingWriter, WriteThrough};
+pub use crate::token::{CloseReceipt, CloseToken, TokenClosable};
+pub use crate::tracked::TrackedFile;
+pub use crate::transaction::FileTransaction;
+pub use crate::try_close::{TryClosable, TryCloseError};
+pub use crate::verify::{ExpectedLen, VerifyAfterClose};
+pub use crate::witness::{Closed, Open};
+pub use crate::write::{
+    append_and_close, append_and_close_with, copy_durable, with_create, with_file, write_and_close,
+    write_and_close_with, WriteError,
+};
 
-    /// Returns the file descriptor assigned to the file
-    ///
-    /// This should only be used in very rare cases. Check you OS documentation before use.
-    ///
-    /// OBS: This function is OS specific for windows systems
-    #[cfg(windows)]
-    pub fn raw_handle(&self) -> RawHandle {
-        self.handle
-    }
+#[cfg(feature = "derive")]
+pub use close_file_derive::Closable;
+
+use std::path::PathBuf;
 
-    /// Returns the error produced when the file was closed.
-    pub fn as_io_error(&self) -> &io::Error {
-        &self.io_error
+#[doc(hidden)]
+pub mod __private {
+    use crate::{CloseError, CloseErrors};
+
+    /// Collects the results of closing the fields of a struct with derive(Closable)
+    #[track_caller]
+    pub fn close_fields<const N: usize>(
+        results: [(usize, Result<(), CloseError>); N],
+    ) -> Result<(), CloseError> {
+        let mut errors = CloseErrors::new();
+        for (index, result) in IntoIterator::into_iter(results) {
+            if let Err(e) = result {
+                errors.push(index, e);
+            }
+        }
+        match errors.into_result() {
+            Ok(()) => Ok(()),
+            Err(errors) => Err(CloseError::from_errors(errors)),
+        }
     }
 }
 
-impl std::error::Error for CloseError {}
-
+/// A resource that can be closed, reporting errors
+///
+/// With the `derive` feature, `#[derive(Closable)]` implements it for structs of closable fields.
+/// All fields are closed, even if closing an earlier one failed, and the errors are aggregated like
+/// for tuples, with the positions of the fields as indices. The fields are closed in declaration
+/// order, fields with `#[close(order = N)]` before the others by ascending N. Fields with
+/// `#[close(skip)]` are dropped.
+///
+/// ```
+/// # #[cfg(feature = "derive")]
+/// # {
+/// use close_file::Closable;
+///
+/// #[derive(Closable)]
+/// struct Table {
+///     data: std::fs::File,
+///     #[close(order = 0)]
+///     index: std::fs::File,
+///     #[close(skip)]
+///     name: String,
+/// }
+/// # }
+/// ```
 pub trait Closable {
+    #[track_caller]
     fn close(self) -> Result<(), CloseError>;
+
+    /// Closes like close(), attaching the given path to the error on failure
+    #[track_caller]
+    fn close_with_path<P: Into<PathBuf>>(self, path: P) -> Result<(), CloseError>
+    where
+        Self: Sized,
+    {
+        self.close().map_err(|e| e.with_path(path))
+    }
 }
 
-impl fmt::Display for CloseError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        fmt::Display::fmt(&self.io_error, f)
+/// Closes resources through a trait object, e.g. in a collection of different resources
+///
+/// Closable::close() takes the resource by value, so it can't be called on a `dyn Closable`. Every
+/// Closable is a DynClosable, and `Box<dyn DynClosable>` is Closable again.
+///
+/// ```
+/// use close_file::{Closable, DynClosable};
+///
+/// let (reader, writer) = std::io::pipe().unwrap();
+/// let resources: Vec<Box<dyn DynClosable>> = vec![
+///     Box::new(std::fs::File::create("temp").unwrap()),
+///     Box::new(reader),
+///     Box::new(writer),
+/// ];
+/// resources.close().unwrap();
+/// ```
+pub trait DynClosable {
+    /// Closes the boxed resource like Closable::close()
+    #[track_caller]
+    fn close_boxed(self: Box<Self>) -> Result<(), CloseError>;
+}
+
+impl<T: Closable> DynClosable for T {
+    #[track_caller]
+    fn close_boxed(self: Box<Self>) -> Result<(), CloseError> {
+        (*self).close()
     }
 }
 
-impl fmt::Debug for CloseError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        fmt::Debug::fmt(&self.io_error, f)
+impl Closable for Box<dyn DynClosable + '_> {
+    #[track_caller]
+    fn close(self) -> Result<(), CloseError> {
+        self.close_boxed()
     }
 }
 
-#[cfg(unix)]
-mod imp {
-    use crate::CloseError;
-    use std::os::unix::prelude::*;
-    use std::{fs, io};
-
-    impl crate::Closable for fs::File {
-        fn close(self) -> Result<(), CloseError> {
-            let fd = self.into_raw_fd();
-            if unsafe { libc::close(fd) } != 0 {
-                return Err(CloseError {
-                    io_error: io::Error::last_os_error(),
-                    fd,
-                });
-            }
-            Ok(())
-        }
+impl Closable for Box<dyn DynClosable + Send + '_> {
+    #[track_caller]
+    fn close(self) -> Result<(), CloseError> {
+        self.close_boxed()
     }
 }
 
-#[cfg(windows)]
-mod imp {
-    use crate::CloseError;
-    use std::os::windows::prelude::*;
-    use std::{fs, io};
-
-    impl crate::Closable for fs::File {
-        fn close(self) -> Result<(), CloseError> {
-            let handle = self.into_raw_handle();
-            let rc = unsafe { kernel32::CloseHandle(handle) };
-            if rc != 0 {
-                Ok(())
-            } else {
-                Err(CloseError {
-                    io_error: io::Error::last_os_error(),
-                    handle,
-                })
-            }
+/// A resource whose close can fail with an error type of its own
+///
+/// Closable always fails with a CloseError, which can't describe the failures of e.g. compressors
+/// or archive writers that finish their output when closed. Every Closable is a CheckedClose with
+/// CloseError as the error, so code generic over CheckedClose works with both.
+///
+/// ```
+/// use close_file::CheckedClose;
+///
+/// struct Archive;
+///
+/// impl CheckedClose for Archive {
+///     type Error = std::fmt::Error;
+///
+///     fn checked_close(self) -> Result<(), std::fmt::Error> {
+///         Ok(())
+///     }
+/// }
+///
+/// fn finish<T: CheckedClose>(output: T) -> Result<(), T::Error> {
+///     output.checked_close()
+/// }
+///
+/// finish(Archive).unwrap();
+/// finish(std::fs::File::create("temp").unwrap()).unwrap();
+/// ```
+pub trait CheckedClose {
+    /// The error of a failed close
+    type Error;
+
+    #[track_caller]
+    fn checked_close(self) -> Result<(), Self::Error>;
+}
+
+impl<T: Closable> CheckedClose for T {
+    type Error = CloseError;
+
+    #[track_caller]
+    fn checked_close(self) -> Result<(), CloseError> {
+        self.close()
+    }
+}
+
+/// Closes the value if there is one, None is closed successfully
+impl<T: Closable> Closable for Option<T> {
+    #[track_caller]
+    fn close(self) -> Result<(), CloseError> {
+        match self {
+            Some(value) => value.close(),
+            None => Ok(()),
         }
     }
 }
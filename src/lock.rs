@@ -0,0 +1,107 @@
+use crate::{imp, Closable, CloseError, CloseOptions, CloseStage, SyncClosable};
+use std::fs;
+use std::io::{self, Read, Seek, Write};
+use std::ops::Deref;
+
+/// A file holding an advisory lock, which is released when the file is closed
+///
+/// The lock is taken with flock(2) on unix and LockFileEx on windows and covers the whole file.
+/// On windows the lock is enforced, other handles can't read or write a locked range. Solaris and
+/// illumos use fcntl(F_SETLKW) instead, where an exclusive lock needs a file opened for writing, a
+/// shared lock one opened for reading, and closing any descriptor of the file releases the lock.
+/// close() releases the lock, then closes the file. If releasing fails, the file is still closed,
+/// which releases the lock as well, and the error has the stage CloseStage::Unlock.
+///
+/// ```
+/// use close_file::{Closable, LockedFile};
+/// use std::io::Write;
+///
+/// let mut f = LockedFile::lock(std::fs::File::create("temp").unwrap()).unwrap();
+/// f.write_all(b"Hello, world!").unwrap();
+/// f.close().unwrap();
+/// ```
+#[derive(Debug)]
+pub struct LockedFile {
+    inner: fs::File,
+}
+
+impl LockedFile {
+    /// Takes an exclusive lock on the file, blocking until it is available
+    pub fn lock(file: fs::File) -> io::Result<LockedFile> {
+        imp::lock(&file, true)?;
+        Ok(LockedFile { inner: file })
+    }
+
+    /// Takes a shared lock on the file, blocking until it is available
+    pub fn lock_shared(file: fs::File) -> io::Result<LockedFile> {
+        imp::lock(&file, false)?;
+        Ok(LockedFile { inner: file })
+    }
+
+    /// Releases the lock and returns the file, which stays open
+    ///
+    /// If releasing fails, the locked file is handed back with the error, so unlocking can be
+    /// retried or the file closed.
+    pub fn unlock(self) -> Result<fs::File, (io::Error, LockedFile)> {
+        match imp::unlock(&self.inner) {
+            Ok(()) => Ok(self.inner),
+            Err(e) => Err((e, self)),
+        }
+    }
+}
+
+impl Deref for LockedFile {
+    type Target = fs::File;
+
+    fn deref(&self) -> &fs::File {
+        &self.inner
+    }
+}
+
+impl Read for LockedFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.inner.read(buf)
+    }
+}
+
+impl Write for LockedFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.inner.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl Seek for LockedFile {
+    fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+        self.inner.seek(pos)
+    }
+}
+
+impl Closable for LockedFile {
+    #[track_caller]
+    fn close(self) -> Result<(), CloseError> {
+        if let Err(e) = imp::unlock(&self.inner) {
+            let _ = self.inner.close();
+            return Err(CloseError::detached(e, CloseStage::Unlock));
+        }
+        self.inner.close()
+    }
+}
+
+/// Releases the lock, then applies the options and closes the file
+///
+/// Other processes see the written data as soon as they take the lock, syncing only makes it
+/// durable, so it doesn't need to happen under the lock.
+impl SyncClosable for LockedFile {
+    #[track_caller]
+    fn close_with_options(self, options: &CloseOptions) -> Result<(), CloseError> {
+        if let Err(e) = imp::unlock(&self.inner) {
+            let _ = self.inner.close_with_options(options);
+            return Err(CloseError::detached(e, CloseStage::Unlock));
+        }
+        self.inner.close_with_options(options)
+    }
+}
@@ -0,0 +1,123 @@
+use crate::{Closable, CloseError, CloseOptions, SyncClosable};
+use std::ops::{Deref, DerefMut};
+use std::panic::Location;
+
+/// What MustClose does when it is dropped without being closed or defused
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum DropAction {
+    /// Panics, unless the thread is already panicking
+    Panic,
+    /// Passes an error of the kind CloseErrorKind::Other to the handler of
+    /// set_drop_close_error_handler(), or prints a message to stderr if no handler is set
+    ///
+    /// Panics in strict mode instead, see set_strict().
+    Log,
+    /// Drops the value silently
+    Ignore,
+}
+
+impl Default for DropAction {
+    /// Panic in debug builds, Log in release builds
+    fn default() -> Self {
+        if cfg!(debug_assertions) {
+            DropAction::Panic
+        } else {
+            DropAction::Log
+        }
+    }
+}
+
+/// A wrapper that must be closed explicitly
+///
+/// Dropping it without calling close() or defuse() is treated as a bug, see DropAction. The value
+/// is dropped afterwards, e.g. a file is closed without checking errors.
+///
+/// ```
+/// use close_file::{Closable, MustClose};
+/// use std::io::Write;
+///
+/// let mut f = MustClose::new(std::fs::File::create("temp").unwrap());
+/// f.write_all(b"Hello, world!").unwrap();
+/// f.close().unwrap();
+/// ```
+#[derive(Debug)]
+pub struct MustClose<T> {
+    // Only None after close() or defuse()
+    inner: Option<T>,
+    on_drop: DropAction,
+    created_at: &'static Location<'static>,
+}
+
+impl<T> MustClose<T> {
+    /// Wraps the value with the default DropAction
+    #[track_caller]
+    pub fn new(inner: T) -> Self {
+        MustClose::with_drop_action(inner, DropAction::default())
+    }
+
+    /// Wraps the value with the given DropAction
+    #[track_caller]
+    pub fn with_drop_action(inner: T, on_drop: DropAction) -> Self {
+        MustClose {
+            inner: Some(inner),
+            on_drop,
+            created_at: Location::caller(),
+        }
+    }
+
+    /// Returns the value, so it can be dropped without closing it
+    pub fn defuse(mut self) -> T {
+        self.take()
+    }
+
+    fn take(&mut self) -> T {
+        self.inner.take().expect("the value is only taken once")
+    }
+}
+
+impl<T> Deref for MustClose<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.inner.as_ref().expect("the value is only taken once")
+    }
+}
+
+impl<T> DerefMut for MustClose<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.inner.as_mut().expect("the value is only taken once")
+    }
+}
+
+impl<T: Closable> Closable for MustClose<T> {
+    #[track_caller]
+    fn close(mut self) -> Result<(), CloseError> {
+        self.take().close()
+    }
+}
+
+impl<T: SyncClosable> SyncClosable for MustClose<T> {
+    #[track_caller]
+    fn close_with_options(mut self, options: &CloseOptions) -> Result<(), CloseError> {
+        self.take().close_with_options(options)
+    }
+}
+
+impl<T> Drop for MustClose<T> {
+    fn drop(&mut self) {
+        if self.inner.is_none() {
+            return;
+        }
+        let message = format!(
+            "{} created at {} was dropped without being closed",
+            std::any::type_name::<Self>(),
+            self.created_at
+        );
+        match self.on_drop {
+            DropAction::Panic => crate::strict::report_unclosed::<Self>(message, true),
+            DropAction::Log => crate::strict::report_unclosed::<Self>(message, false),
+            DropAction::Ignore => {}
+        }
+    }
+}
@@ -0,0 +1,154 @@
+use crate::{imp, Closable, CloseError, CloseStage};
+use std::io::{self, Read};
+use std::net::{Shutdown, TcpStream};
+use std::time::{Duration, Instant};
+#[cfg(unix)]
+use std::{fs, os::unix::net::UnixListener};
+
+/// Options for closing a TCP connection without losing data
+///
+/// Shuts down the write side, so the peer sees the end of the stream, drains the read side until
+/// the peer closes its side too, applies SO_LINGER if configured and closes the socket. Closing a
+/// socket with unread data makes the OS reset the connection, which can discard data the peer has
+/// not read yet.
+///
+/// ```no_run
+/// use close_file::GracefulClose;
+/// use std::time::Duration;
+///
+/// let stream = std::net::TcpStream::connect("127.0.0.1:8080").unwrap();
+/// GracefulClose::new(Duration::from_secs(5))
+///     .linger(Duration::from_secs(1))
+///     .close(stream)
+///     .unwrap();
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GracefulClose {
+    pub(crate) timeout: Duration,
+    pub(crate) drain: bool,
+    pub(crate) linger: Option<Duration>,
+}
+
+impl GracefulClose {
+    /// Creates options that drain for at most the timeout and leave SO_LINGER as it is
+    pub fn new(timeout: Duration) -> Self {
+        GracefulClose {
+            timeout,
+            drain: true,
+            linger: None,
+        }
+    }
+
+    /// Sets whether to read and discard data until the peer closes its side, defaults to true
+    pub fn drain(mut self, drain: bool) -> Self {
+        self.drain = drain;
+        self
+    }
+
+    /// Sets SO_LINGER, so closing waits at most the duration for unsent data to be sent
+    ///
+    /// SO_LINGER has a resolution of seconds, so the duration is rounded up to whole seconds. A
+    /// duration of zero makes closing reset the connection instead.
+    pub fn linger(mut self, linger: Duration) -> Self {
+        self.linger = Some(linger);
+        self
+    }
+
+    /// Closes the stream gracefully
+    ///
+    /// If a step before closing fails, the stream is still closed and the error has the stage of
+    /// the step, i.e. CloseStage::Shutdown, CloseStage::Drain or CloseStage::Linger. Draining
+    /// fails with CloseErrorKind::TimedOut if the peer didn't close its side within the timeout.
+    #[track_caller]
+    pub fn close(&self, mut stream: TcpStream) -> Result<(), CloseError> {
+        if let Err(e) = stream.shutdown(Shutdown::Write) {
+            return close_after(stream, e, CloseStage::Shutdown);
+        }
+        if self.drain {
+            if let Err(e) = drain(&mut stream, self.timeout) {
+                return close_after(stream, e, CloseStage::Drain);
+            }
+        }
+        if let Some(linger) = self.linger {
+            if let Err(e) = imp::set_linger(&stream, linger) {
+                return close_after(stream, e, CloseStage::Linger);
+            }
+        }
+        stream.close()
+    }
+}
+
+/// Converts the linger duration to the whole seconds of SO_LINGER, rounding up
+///
+/// Truncating would turn a sub-second linger into zero, which resets the connection.
+pub(crate) fn linger_secs(linger: Duration) -> u64 {
+    let round_up = (linger.subsec_nanos() > 0) as u64;
+    linger.as_secs().saturating_add(round_up)
+}
+
+/// Closes the stream gracefully, draining for at most the timeout
+///
+/// See GracefulClose.
+#[track_caller]
+pub fn graceful_close(stream: TcpStream, timeout: Duration) -> Result<(), CloseError> {
+    GracefulClose::new(timeout).close(stream)
+}
+
+/// Reads and discards data until the end of the stream or the deadline
+fn drain(stream: &mut TcpStream, timeout: Duration) -> io::Result<()> {
+    let deadline = Instant::now() + timeout;
+    let mut buf = [0u8; 4096];
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining == Duration::ZERO {
+            return Err(io::ErrorKind::TimedOut.into());
+        }
+        stream.set_read_timeout(Some(remaining))?;
+        match stream.read(&mut buf) {
+            Ok(0) => return Ok(()),
+            Ok(_) => {}
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            // Read timeouts are reported as WouldBlock on unix and TimedOut on Windows
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) =>
+            {
+                return Err(io::ErrorKind::TimedOut.into())
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Closes the stream after a failed step, reporting the error of the step
+#[track_caller]
+fn close_after(stream: TcpStream, e: io::Error, stage: CloseStage) -> Result<(), CloseError> {
+    let _ = stream.close();
+    Err(CloseError::detached(e, stage))
+}
+
+/// Closes the listener and removes the socket file it was bound to
+///
+/// Unix domain sockets bound to a path leave the socket file behind, which makes binding the path
+/// again fail with EADDRINUSE. Listeners of unnamed or abstract addresses are only closed. The
+/// file is removed even if closing fails, a failed removal is reported with the stage
+/// CloseStage::Remove.
+#[cfg(unix)]
+#[track_caller]
+pub fn close_and_unlink(listener: UnixListener) -> Result<(), CloseError> {
+    let path = listener
+        .local_addr()
+        .ok()
+        .and_then(|addr| addr.as_pathname().map(|path| path.to_owned()));
+    let result = listener.close();
+    if let Some(path) = path {
+        if let Err(e) = fs::remove_file(&path) {
+            if result.is_ok() && e.kind() != io::ErrorKind::NotFound {
+                return Err(CloseError::detached(e, CloseStage::Remove).with_path(path));
+            }
+        }
+    }
+    result
+}
@@ -0,0 +1,60 @@
+use crate::{Cleanup, CloseError};
+use std::ffi::OsString;
+use std::{fs, io};
+
+/// What to do with a file whose close pipeline failed
+///
+/// The policy needs the path of the file, which is taken from the error, see
+/// CloseError::with_path(). If the error still owns the file, it is closed first, so the error can
+/// no longer be retried. The outcome is recorded in the error, see CloseError::cleanup(). Errors
+/// without a path, e.g. of std::fs::File, keep the file and get Cleanup::Failed with an error of
+/// the kind io::ErrorKind::InvalidInput, so a policy that couldn't be applied is not missed.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum FailurePolicy {
+    /// Leave the file as it is
+    #[default]
+    Keep,
+    /// Remove the file, e.g. so that consumers don't read a partial file
+    Delete,
+    /// Rename the file by appending the suffix to its name, to keep it for debugging
+    ///
+    /// An existing file with the new name is replaced.
+    KeepWithSuffix(OsString),
+}
+
+impl FailurePolicy {
+    /// Keeps the file with the suffix ".partial", e.g. "out.csv" is renamed to "out.csv.partial"
+    pub fn keep_partial() -> FailurePolicy {
+        FailurePolicy::KeepWithSuffix(".partial".into())
+    }
+
+    /// Applies the policy to the file the error belongs to
+    pub(crate) fn apply(&self, err: &mut CloseError) {
+        if *self == FailurePolicy::Keep {
+            return;
+        }
+        let path = match err.path() {
+            Some(path) => path.to_path_buf(),
+            None => {
+                err.set_cleanup(Cleanup::Failed(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "the path of the file is unknown",
+                )));
+                return;
+            }
+        };
+        drop(err.take_file());
+        let cleanup = match self {
+            FailurePolicy::Keep => return,
+            FailurePolicy::Delete => fs::remove_file(&path).map(|_| Cleanup::Removed),
+            FailurePolicy::KeepWithSuffix(suffix) => {
+                let mut name = path.file_name().unwrap_or_default().to_os_string();
+                name.push(suffix);
+                let renamed = path.with_file_name(name);
+                fs::rename(&path, &renamed).map(|_| Cleanup::Renamed(renamed))
+            }
+        };
+        err.set_cleanup(cleanup.unwrap_or_else(Cleanup::Failed));
+    }
+}
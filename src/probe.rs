@@ -0,0 +1,218 @@
+use crate::{imp, Durability};
+use std::path::Path;
+use std::{fs, io};
+
+/// The type of a file system, as far as it matters for closing and syncing files
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize),
+    serde(rename_all = "snake_case")
+)]
+#[non_exhaustive]
+pub enum FileSystem {
+    /// ext2, ext3 or ext4
+    Ext,
+    Xfs,
+    Btrfs,
+    Zfs,
+    F2fs,
+    /// tmpfs or ramfs, which only live in memory
+    Tmpfs,
+    Nfs,
+    /// SMB or CIFS network shares
+    Smb,
+    /// A file system in userspace, which may be backed by anything
+    Fuse,
+    /// An overlay of other file systems, e.g. for containers
+    Overlay,
+    Apfs,
+    Hfs,
+    Ntfs,
+    Refs,
+    /// FAT, FAT32 or exFAT
+    Fat,
+    /// A file system not known to this crate
+    Unknown,
+}
+
+impl FileSystem {
+    /// Detects the file system the file is on
+    ///
+    /// This uses fstatfs(2) on unix and GetVolumeInformationByHandleW on windows. On unix platforms
+    /// without fstatfs(2), like NetBSD and illumos, this fails with io::ErrorKind::Unsupported.
+    pub fn of(file: &fs::File) -> io::Result<FileSystem> {
+        imp::filesystem_of_file(file)
+    }
+
+    /// Detects the file system the path is on
+    ///
+    /// Like of(), this fails with io::ErrorKind::Unsupported on NetBSD and illumos.
+    pub fn of_path<P: AsRef<Path>>(path: P) -> io::Result<FileSystem> {
+        imp::filesystem(path.as_ref())
+    }
+
+    /// Whether the file system is accessed over the network
+    pub fn is_network(self) -> bool {
+        matches!(self, FileSystem::Nfs | FileSystem::Smb)
+    }
+
+    /// The durability level that should be used to close files on the file system
+    ///
+    /// E.g. files on network file systems are always synced, as errors may otherwise be lost, and
+    /// files on tmpfs are not synced at all. See DurabilityProbe::reason() for the reasoning.
+    pub fn recommended_durability(self) -> Durability {
+        DurabilityProbe::new(self).recommended
+    }
+
+    /// Classifies a file system by the type name reported by the OS
+    #[cfg_attr(
+        not(any(windows, target_vendor = "apple", target_os = "freebsd")),
+        allow(dead_code)
+    )]
+    pub(crate) fn from_name(name: &str) -> FileSystem {
+        match name.to_ascii_lowercase().as_str() {
+            "ext2" | "ext3" | "ext4" => FileSystem::Ext,
+            "xfs" => FileSystem::Xfs,
+            "btrfs" => FileSystem::Btrfs,
+            "zfs" => FileSystem::Zfs,
+            "f2fs" => FileSystem::F2fs,
+            "tmpfs" | "ramfs" => FileSystem::Tmpfs,
+            "nfs" | "nfs4" => FileSystem::Nfs,
+            "smbfs" | "cifs" | "smb2" => FileSystem::Smb,
+            "fuse" | "fusefs" | "macfuse" | "osxfuse" => FileSystem::Fuse,
+            "overlay" | "unionfs" => FileSystem::Overlay,
+            "apfs" => FileSystem::Apfs,
+            "hfs" => FileSystem::Hfs,
+            "ntfs" => FileSystem::Ntfs,
+            "refs" => FileSystem::Refs,
+            "fat" | "fat32" | "msdos" | "msdosfs" | "vfat" | "exfat" => FileSystem::Fat,
+            _ => FileSystem::Unknown,
+        }
+    }
+}
+
+/// How confident the probe is that write-back errors are reported to the file descriptor
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize),
+    serde(rename_all = "snake_case")
+)]
+#[non_exhaustive]
+pub enum Confidence {
+    /// Errors depend on unknown parts, like a FUSE daemon or an unknown file system
+    Low,
+    /// Errors are reported, but with known gaps, e.g. on network file systems
+    Medium,
+    /// Errors are reliably reported by fsync(2) on the file and close(2) after it
+    High,
+}
+
+/// The result of probing the file system of a path, see probe_durability()
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct DurabilityProbe {
+    filesystem: FileSystem,
+    confidence: Confidence,
+    recommended: Durability,
+    reason: &'static str,
+}
+
+impl DurabilityProbe {
+    /// The detected file system
+    pub fn filesystem(&self) -> FileSystem {
+        self.filesystem
+    }
+
+    /// How confident the probe is that write-back errors are reported
+    pub fn confidence(&self) -> Confidence {
+        self.confidence
+    }
+
+    /// The durability level that should be used to close files on the file system
+    pub fn recommended(&self) -> Durability {
+        self.recommended
+    }
+
+    /// A human readable explanation of the confidence and recommendation
+    pub fn reason(&self) -> &'static str {
+        self.reason
+    }
+
+    fn new(filesystem: FileSystem) -> DurabilityProbe {
+        let (confidence, recommended, reason) = match filesystem {
+            FileSystem::Ext | FileSystem::Xfs | FileSystem::Btrfs | FileSystem::F2fs => (
+                Confidence::High,
+                Durability::Sync,
+                "write-back errors are reported to every file descriptor since Linux 4.13",
+            ),
+            FileSystem::Zfs => (
+                Confidence::High,
+                Durability::Sync,
+                "fsync waits for the intent log, errors are reported",
+            ),
+            FileSystem::Tmpfs => (
+                Confidence::High,
+                Durability::None,
+                "the file system only lives in memory, syncing has no effect",
+            ),
+            FileSystem::Nfs | FileSystem::Smb => (
+                Confidence::Medium,
+                Durability::Sync,
+                "errors may only be reported on close and the server may lose acknowledged data",
+            ),
+            FileSystem::Overlay => (
+                Confidence::Medium,
+                Durability::Sync,
+                "errors are reported by the upper file system, older kernels didn't always pass \
+                 them through",
+            ),
+            FileSystem::Apfs | FileSystem::Hfs => (
+                Confidence::Medium,
+                Durability::FullSync,
+                "fsync doesn't flush the drive cache, F_FULLFSYNC is needed against power loss",
+            ),
+            FileSystem::Ntfs | FileSystem::Refs => (
+                Confidence::High,
+                Durability::FullSync,
+                "FlushFileBuffers reports errors and flushes the drive cache",
+            ),
+            FileSystem::Fat => (
+                Confidence::Medium,
+                Durability::FullSync,
+                "the file system has no journal, a crash while syncing can corrupt it",
+            ),
+            FileSystem::Fuse => (
+                Confidence::Low,
+                Durability::FullSync,
+                "error reporting depends on the userspace file system daemon",
+            ),
+            FileSystem::Unknown => (
+                Confidence::Low,
+                Durability::FullSync,
+                "the file system is not known, the strongest durability level is recommended",
+            ),
+        };
+        DurabilityProbe {
+            filesystem,
+            confidence,
+            recommended,
+            reason,
+        }
+    }
+}
+
+/// Probes whether closing files under the given path reports write-back errors
+///
+/// The file system of the path is detected with statfs(2) on unix and GetVolumeInformationW on
+/// windows and looked up in a table of known quirks. This is meant to be called once at startup,
+/// e.g. to refuse to run on file systems with low confidence or to choose the durability level.
+///
+/// ```
+/// let probe = close_file::probe_durability(std::env::temp_dir()).unwrap();
+/// println!("{:?}: {}", probe.filesystem(), probe.reason());
+/// ```
+pub fn probe_durability<P: AsRef<Path>>(path: P) -> io::Result<DurabilityProbe> {
+    Ok(DurabilityProbe::new(FileSystem::of_path(path)?))
+}
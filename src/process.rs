@@ -0,0 +1,130 @@
+use crate::{Closable, CloseError};
+use std::io;
+use std::process::{Child, ExitStatus};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Error-checked teardown of a child process
+///
+/// Closes the pipe to the stdin of the child, so it sees the end of its input, waits for it to
+/// exit and closes the pipes from its stdout and stderr. Output that was not read yet is
+/// discarded, read it before, as a child blocked on a full pipe never exits.
+///
+/// ```no_run
+/// use close_file::ChildExt;
+/// use std::process::{Command, Stdio};
+///
+/// let mut child = Command::new("sort").stdin(Stdio::piped()).spawn().unwrap();
+/// let teardown = child.close_streams().unwrap();
+/// assert!(teardown.status().success());
+/// teardown.into_result().unwrap();
+/// ```
+pub trait ChildExt {
+    /// Closes the streams of the child and waits for it to exit
+    #[track_caller]
+    fn close_streams(&mut self) -> io::Result<ChildTeardown>;
+
+    /// Like close_streams(), but waits for at most the timeout
+    ///
+    /// If the child didn't exit in time, an error of the kind io::ErrorKind::TimedOut is returned
+    /// and the child keeps running, e.g. to kill it. Stdin is closed in any case, if closing it
+    /// failed, the CloseError is the inner error of the timeout error.
+    #[track_caller]
+    fn close_streams_timeout(&mut self, timeout: Duration) -> io::Result<ChildTeardown>;
+}
+
+impl ChildExt for Child {
+    #[track_caller]
+    fn close_streams(&mut self) -> io::Result<ChildTeardown> {
+        let stdin = close_stream(self.stdin.take());
+        let status = self.wait()?;
+        Ok(close_output(self, status, stdin))
+    }
+
+    #[track_caller]
+    fn close_streams_timeout(&mut self, timeout: Duration) -> io::Result<ChildTeardown> {
+        let stdin = close_stream(self.stdin.take());
+        let deadline = Instant::now() + timeout;
+        let mut delay = Duration::from_millis(1);
+        let status = loop {
+            if let Some(status) = self.try_wait()? {
+                break status;
+            }
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining == Duration::ZERO {
+                return Err(match stdin {
+                    Some(e) => io::Error::new(io::ErrorKind::TimedOut, e),
+                    None => io::ErrorKind::TimedOut.into(),
+                });
+            }
+            thread::sleep(delay.min(remaining));
+            delay = (delay * 2).min(Duration::from_millis(100));
+        };
+        Ok(close_output(self, status, stdin))
+    }
+}
+
+/// Closes stdout and stderr of the exited child
+#[track_caller]
+fn close_output(child: &mut Child, status: ExitStatus, stdin: Option<CloseError>) -> ChildTeardown {
+    ChildTeardown {
+        status,
+        stdin,
+        stdout: close_stream(child.stdout.take()),
+        stderr: close_stream(child.stderr.take()),
+    }
+}
+
+#[track_caller]
+fn close_stream<T: Closable>(stream: Option<T>) -> Option<CloseError> {
+    // Not a closure, so the error has the location of the caller
+    match stream {
+        Some(stream) => stream.close().err(),
+        None => None,
+    }
+}
+
+/// The exit status of a child and the errors of closing its streams, see ChildExt
+#[derive(Debug)]
+pub struct ChildTeardown {
+    status: ExitStatus,
+    stdin: Option<CloseError>,
+    stdout: Option<CloseError>,
+    stderr: Option<CloseError>,
+}
+
+impl ChildTeardown {
+    /// Returns the exit status of the child
+    pub fn status(&self) -> ExitStatus {
+        self.status
+    }
+
+    /// Returns the error of closing stdin, in which case the child may not have seen the end of
+    /// its input
+    pub fn stdin_error(&self) -> Option<&CloseError> {
+        self.stdin.as_ref()
+    }
+
+    /// Returns the error of closing stdout
+    pub fn stdout_error(&self) -> Option<&CloseError> {
+        self.stdout.as_ref()
+    }
+
+    /// Returns the error of closing stderr
+    pub fn stderr_error(&self) -> Option<&CloseError> {
+        self.stderr.as_ref()
+    }
+
+    /// Returns true if all streams were closed successfully
+    pub fn is_ok(&self) -> bool {
+        self.stdin.is_none() && self.stdout.is_none() && self.stderr.is_none()
+    }
+
+    /// Returns the exit status, or the first error of closing stdin, stdout and stderr
+    pub fn into_result(self) -> Result<ExitStatus, CloseError> {
+        match self.stdin.or(self.stdout).or(self.stderr) {
+            Some(e) => Err(e),
+            None => Ok(self.status),
+        }
+    }
+}
@@ -0,0 +1,42 @@
+//! Checked close of any type that owns a file descriptor/handle
+//!
+//! OBS: This module is only available with the `raw` feature
+//!
+//! A blanket Closable impl for all such types would overlap with the impls of this crate, so
+//! third-party types are closed with close_raw_owner() or wrapped into RawOwner instead.
+//!
+//! On Windows only types owning a kernel handle (IntoRawHandle) are supported. Sockets must be
+//! closed with closesocket, use the Closable impl of OwnedSocket for them.
+
+use crate::{imp, Closable, CloseError};
+
+#[cfg(unix)]
+use std::os::unix::io::{FromRawFd, IntoRawFd as IntoRaw, OwnedFd as Owned};
+#[cfg(windows)]
+use std::os::windows::io::{FromRawHandle, IntoRawHandle as IntoRaw, OwnedHandle as Owned};
+
+/// Closes the file descriptor/handle owned by the value
+///
+/// ```
+/// let f = std::fs::File::create("temp").unwrap();
+/// close_file::raw::close_raw_owner(f).unwrap();
+/// ```
+#[track_caller]
+pub fn close_raw_owner<T: IntoRaw>(owner: T) -> Result<(), CloseError> {
+    #[cfg(unix)]
+    let owned = unsafe { Owned::from_raw_fd(owner.into_raw_fd()) };
+    #[cfg(windows)]
+    let owned = unsafe { Owned::from_raw_handle(owner.into_raw_handle()) };
+    imp::close(owned)
+}
+
+/// Makes any type owning a file descriptor/handle Closable, see close_raw_owner()
+#[derive(Debug)]
+pub struct RawOwner<T>(pub T);
+
+impl<T: IntoRaw> Closable for RawOwner<T> {
+    #[track_caller]
+    fn close(self) -> Result<(), CloseError> {
+        close_raw_owner(self.0)
+    }
+}
@@ -0,0 +1,117 @@
+use crate::{Closable, CloseError, CloseErrors};
+use std::cell::RefCell;
+use std::marker::PhantomData;
+
+type Closer<'env> = Box<dyn FnOnce() -> Result<(), CloseError> + 'env>;
+
+/// Resources that are closed at the end of a scope, see scope()
+pub struct CloseScope<'env> {
+    closers: RefCell<Vec<Closer<'env>>>,
+    // Invariant over 'env like std::thread::Scope, so registered resources outlive the scope
+    env: PhantomData<&'env mut &'env ()>,
+}
+
+impl<'env> CloseScope<'env> {
+    /// Takes ownership of the resource, so it is closed at the end of the scope
+    ///
+    /// The returned reference can be used like the resource until the end of the scope.
+    // Every resource has its own allocation, like in an arena
+    #[allow(clippy::mut_from_ref)]
+    pub fn register<T: Closable + 'env>(&self, resource: T) -> &mut T {
+        let resource = Box::into_raw(Box::new(resource));
+        // The box is only taken back after the closure passed to scope() returned, so there are no
+        // references to the resource left
+        self.closers
+            .borrow_mut()
+            .push(Box::new(move || unsafe { Box::from_raw(resource) }.close()));
+        unsafe { &mut *resource }
+    }
+
+    /// Closes the resources in reverse order of their registration
+    fn close(&self) -> Result<(), CloseErrors> {
+        let closers = std::mem::take(&mut *self.closers.borrow_mut());
+        let mut errors = CloseErrors::new();
+        for (index, closer) in closers.into_iter().enumerate().rev() {
+            if let Err(e) = closer() {
+                errors.push(index, e);
+            }
+        }
+        errors.into_result()
+    }
+}
+
+impl Drop for CloseScope<'_> {
+    fn drop(&mut self) {
+        // Closes the resources if the closure panicked, the errors can't be reported
+        let _ = self.close();
+    }
+}
+
+/// Runs the closure and closes all resources registered with it afterwards
+///
+/// The resources are closed in reverse order of their registration, even if the closure failed.
+/// If the closure succeeded, but closing failed, the error is converted into the error type of the
+/// closure, see CloseError::errors(). If the closure failed, its error is returned and failures of
+/// closing are discarded.
+///
+/// ```
+/// use std::io::Write;
+///
+/// close_file::scope(|s| {
+///     let f = s.register(std::fs::File::create("temp")?);
+///     f.write_all(b"Hello, world!")?;
+///     Ok::<_, std::io::Error>(())
+/// })
+/// .unwrap();
+/// ```
+pub fn scope<'env, F, T, E>(f: F) -> Result<T, E>
+where
+    F: for<'scope> FnOnce(&'scope CloseScope<'env>) -> Result<T, E>,
+    E: From<CloseError>,
+{
+    let scope = CloseScope {
+        closers: RefCell::new(Vec::new()),
+        env: PhantomData,
+    };
+    let result = f(&scope);
+    let closed = scope.close();
+    let value = result?;
+    match closed {
+        Ok(()) => Ok(value),
+        Err(errors) => Err(CloseError::from_errors(errors).into()),
+    }
+}
+
+/// Closes the files opened at the start of the block at its end, merging errors into its result
+///
+/// Like Go's defer, but the errors of closing are not lost: The resources are registered with
+/// scope(), the block is the body of its closure. The bindings are mutable references to the
+/// resources. Like in a closure, `?` and `return` leave the block, not the enclosing function.
+///
+/// ```
+/// use close_file::closing;
+/// use std::io::Write;
+///
+/// fn write() -> std::io::Result<()> {
+///     closing! {
+///         let data = std::fs::File::create("temp")?;
+///         let log = std::fs::File::create("temp")?;
+///         => {
+///             data.write_all(b"Hello, world!")?;
+///             log.write_all(b"wrote data")?;
+///             Ok(())
+///         }
+///     }
+/// }
+///
+/// write().unwrap();
+/// ```
+#[macro_export]
+macro_rules! closing {
+    ($(let $pat:pat = $init:expr;)* => $body:block) => {
+        $crate::scope(|scope| {
+            $(let $pat = scope.register($init);)*
+            $body
+        })
+    };
+}
@@ -0,0 +1,162 @@
+use crate::{Closable, CloseError, Durability, SyncClosable};
+use std::{error, fmt, io};
+
+type Step<'a> = Box<dyn FnOnce() -> Result<(), CloseError> + 'a>;
+
+/// Closes resources one after the other, aborting at the first failure
+///
+/// Each step only runs if all steps before it succeeded, e.g. the manifest of a database is only
+/// written and closed once the data file is synced and closed. The steps after a failed one are
+/// dropped without running them, resources of skipped steps are dropped without checking errors.
+///
+/// ```
+/// use close_file::{Closable, CloseSequence, Durability};
+/// use std::io::Write;
+///
+/// let data = std::fs::File::create("temp").unwrap();
+/// CloseSequence::new()
+///     .then_synced("data", data, Durability::Sync)
+///     .then_with("manifest", || {
+///         let mut manifest = std::fs::File::create("temp").unwrap();
+///         manifest.write_all(b"v1").unwrap();
+///         manifest.close()
+///     })
+///     .run()
+///     .unwrap();
+/// ```
+#[derive(Default)]
+pub struct CloseSequence<'a> {
+    steps: Vec<(String, Step<'a>)>,
+}
+
+impl<'a> CloseSequence<'a> {
+    /// Creates an empty sequence
+    pub fn new() -> Self {
+        CloseSequence { steps: Vec::new() }
+    }
+
+    /// Appends a step that closes the resource
+    pub fn then<L: Into<String>, T: Closable + 'a>(mut self, label: L, resource: T) -> Self {
+        self.steps
+            .push((label.into(), Box::new(move || resource.close())));
+        self
+    }
+
+    /// Appends a step that syncs the resource with the durability level and closes it
+    pub fn then_synced<L: Into<String>, T: SyncClosable + 'a>(
+        mut self,
+        label: L,
+        resource: T,
+        durability: Durability,
+    ) -> Self {
+        self.steps.push((
+            label.into(),
+            Box::new(move || resource.close_with(durability)),
+        ));
+        self
+    }
+
+    /// Appends a step that runs the closure, e.g. to write and close a file that depends on the
+    /// steps before
+    pub fn then_with<L: Into<String>, F: FnOnce() -> Result<(), CloseError> + 'a>(
+        mut self,
+        label: L,
+        step: F,
+    ) -> Self {
+        self.steps.push((label.into(), Box::new(step)));
+        self
+    }
+
+    /// Runs the steps in order until one fails
+    pub fn run(self) -> Result<(), SequenceError> {
+        let mut steps = self.steps.into_iter().enumerate();
+        while let Some((index, (label, step))) = steps.next() {
+            if let Err(error) = step() {
+                return Err(SequenceError {
+                    step: Box::new(FailedStep {
+                        index,
+                        label,
+                        error,
+                        skipped: steps.map(|(_, (label, _))| label).collect(),
+                    }),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for CloseSequence<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.steps.iter().map(|(label, _)| label))
+            .finish()
+    }
+}
+
+/// The error of a CloseSequence, with the step that failed and the steps that were skipped
+#[derive(Debug)]
+pub struct SequenceError {
+    // Boxed to keep the error small
+    step: Box<FailedStep>,
+}
+
+#[derive(Debug)]
+struct FailedStep {
+    index: usize,
+    label: String,
+    error: CloseError,
+    skipped: Vec<String>,
+}
+
+impl SequenceError {
+    /// Returns the position of the failed step in the sequence
+    pub fn index(&self) -> usize {
+        self.step.index
+    }
+
+    /// Returns the label of the failed step
+    pub fn label(&self) -> &str {
+        &self.step.label
+    }
+
+    /// Returns the error of the failed step
+    pub fn error(&self) -> &CloseError {
+        &self.step.error
+    }
+
+    /// Returns the labels of the steps that were not run
+    pub fn skipped(&self) -> &[String] {
+        &self.step.skipped
+    }
+
+    /// Consumes the error, returning the error of the failed step
+    pub fn into_error(self) -> CloseError {
+        self.step.error
+    }
+}
+
+impl error::Error for SequenceError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        Some(&self.step.error)
+    }
+}
+
+/// Formats the error, e.g. "step data failed: failed to close fd 3 during sync: Input/output
+/// error (os error 5); skipped manifest"
+impl fmt::Display for SequenceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "step {} failed: {}", self.step.label, self.step.error)?;
+        if !self.step.skipped.is_empty() {
+            write!(f, "; skipped {}", self.step.skipped.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Converts the error into the io::Error of the failed step, see `io::Error::from(CloseError)`
+impl From<SequenceError> for io::Error {
+    fn from(err: SequenceError) -> io::Error {
+        err.step.error.into()
+    }
+}
@@ -0,0 +1,83 @@
+use crate::{Closable, CloseError};
+use std::sync::Arc;
+use std::{error, fmt};
+
+/// The error of close_arc()
+#[derive(Debug)]
+pub enum CloseArcError<T> {
+    /// There are other references, the Arc is handed back
+    StillShared(Arc<T>),
+    /// Closing the value failed
+    Close(CloseError),
+}
+
+impl<T: fmt::Debug> error::Error for CloseArcError<T> {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            CloseArcError::StillShared(_) => None,
+            CloseArcError::Close(e) => Some(e),
+        }
+    }
+}
+
+impl<T> fmt::Display for CloseArcError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CloseArcError::StillShared(arc) => write!(
+                f,
+                "failed to close, there are {} other references",
+                Arc::strong_count(arc) - 1
+            ),
+            CloseArcError::Close(e) => fmt::Display::fmt(e, f),
+        }
+    }
+}
+
+impl<T> From<CloseError> for CloseArcError<T> {
+    fn from(err: CloseError) -> Self {
+        CloseArcError::Close(err)
+    }
+}
+
+/// Closes the value if this is the last reference, otherwise hands the Arc back
+///
+/// ```
+/// use std::sync::Arc;
+///
+/// let f = Arc::new(std::fs::File::create("temp").unwrap());
+/// close_file::close_arc(f).unwrap();
+/// ```
+#[track_caller]
+pub fn close_arc<T: Closable>(arc: Arc<T>) -> Result<(), CloseArcError<T>> {
+    match Arc::try_unwrap(arc) {
+        Ok(value) => match value.close() {
+            Ok(()) => Ok(()),
+            Err(e) => Err(CloseArcError::Close(e)),
+        },
+        Err(arc) => Err(CloseArcError::StillShared(arc)),
+    }
+}
+
+/// Drops the reference, closing the value if it was the last one
+///
+/// Unlike close_arc(), exactly one of several threads releasing their references at the same time
+/// closes the value. To make sure the data is durable before the value is closed by whichever
+/// thread comes last, sync it while it is still shared, e.g. with File::sync_all(), which only
+/// needs a reference.
+///
+/// ```
+/// use std::sync::Arc;
+///
+/// let f = Arc::new(std::fs::File::create("temp").unwrap());
+/// let clone = f.clone();
+/// f.sync_all().unwrap();
+/// close_file::release_arc(f).unwrap();
+/// close_file::release_arc(clone).unwrap();
+/// ```
+#[track_caller]
+pub fn release_arc<T: Closable>(arc: Arc<T>) -> Result<(), CloseError> {
+    match Arc::into_inner(arc) {
+        Some(value) => value.close(),
+        None => Ok(()),
+    }
+}
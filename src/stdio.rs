@@ -0,0 +1,123 @@
+use crate::{imp, CloseError, CloseStage};
+use std::io::{self, Write};
+use std::process::ExitCode;
+
+/// Flushes and closes stdout, reporting errors that would otherwise be lost at exit
+///
+/// Output to a full disk or a closed pipe often only fails when the buffered data is flushed or
+/// the file is closed, which the standard library ignores at exit. Call this at the end of main()
+/// and exit with a non-zero code on failure, like GNU tools do. Output written afterwards is
+/// discarded.
+///
+/// ```no_run
+/// println!("Hello, world!");
+/// if let Err(e) = close_file::close_stdout() {
+///     eprintln!("error: {}", e);
+///     std::process::exit(1);
+/// }
+/// ```
+#[track_caller]
+pub fn close_stdout() -> Result<(), CloseError> {
+    let mut stdout = io::stdout().lock();
+    if let Err(e) = stdout.flush() {
+        return Err(CloseError::detached(e, CloseStage::Flush));
+    }
+    imp::close_stdout()
+}
+
+/// Flushes and closes stderr like close_stdout()
+///
+/// Stderr is unbuffered, so only closing can fail. Output written afterwards is discarded.
+#[track_caller]
+pub fn close_stderr() -> Result<(), CloseError> {
+    let mut stderr = io::stderr().lock();
+    if let Err(e) = stderr.flush() {
+        return Err(CloseError::detached(e, CloseStage::Flush));
+    }
+    imp::close_stderr()
+}
+
+/// Closes stdout and stderr after the body of a program and picks the exit code
+///
+/// If closing stdout fails, the message and the error are printed to stderr and the exit code is
+/// used, unless the body already failed. Failures of closing stderr can't be reported and only
+/// change the exit code.
+///
+/// ```no_run
+/// use close_file::StdioExit;
+/// use std::process::ExitCode;
+///
+/// fn main() -> ExitCode {
+///     StdioExit::new().exit_code(2).run(|| {
+///         println!("Hello, world!");
+///         ExitCode::SUCCESS
+///     })
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StdioExit {
+    exit_code: u8,
+    message: String,
+}
+
+impl StdioExit {
+    /// Creates options that exit with 1 and print "write error" when closing fails
+    pub fn new() -> Self {
+        StdioExit {
+            exit_code: 1,
+            message: "write error".to_owned(),
+        }
+    }
+
+    /// Sets the exit code used when closing stdout or stderr fails
+    pub fn exit_code(mut self, exit_code: u8) -> Self {
+        self.exit_code = exit_code;
+        self
+    }
+
+    /// Sets the message printed before the error when closing stdout fails
+    pub fn message<S: Into<String>>(mut self, message: S) -> Self {
+        self.message = message.into();
+        self
+    }
+
+    /// Runs the body of the program, then closes stdout and stderr
+    pub fn run<F: FnOnce() -> ExitCode>(&self, main: F) -> ExitCode {
+        let code = main();
+        let mut failed = false;
+        if let Err(e) = close_stdout() {
+            let _ = writeln!(io::stderr(), "{}: {}", self.message, e);
+            failed = true;
+        }
+        if close_stderr().is_err() {
+            failed = true;
+        }
+        if failed && code == ExitCode::SUCCESS {
+            ExitCode::from(self.exit_code)
+        } else {
+            code
+        }
+    }
+}
+
+impl Default for StdioExit {
+    fn default() -> Self {
+        StdioExit::new()
+    }
+}
+
+/// Runs the body of the program, then closes stdout and stderr, see StdioExit
+///
+/// ```no_run
+/// use std::process::ExitCode;
+///
+/// fn main() -> ExitCode {
+///     close_file::run(|| {
+///         println!("Hello, world!");
+///         ExitCode::SUCCESS
+///     })
+/// }
+/// ```
+pub fn run<F: FnOnce() -> ExitCode>(main: F) -> ExitCode {
+    StdioExit::new().run(main)
+}
@@ -0,0 +1,36 @@
+use crate::{CloseError, CloseStage};
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static STRICT: AtomicBool = AtomicBool::new(cfg!(all(feature = "strict", debug_assertions)));
+
+/// Sets whether dropping a TrackedFile or MustClose without closing it panics
+///
+/// Strict mode is on by default in debug builds with the `strict` feature, e.g. to enforce closing
+/// files in CI. The panic message names where the file was opened. Threads that are already
+/// panicking report the message like DropAction::Log instead. MustClose with DropAction::Ignore is
+/// still dropped silently.
+pub fn set_strict(strict: bool) {
+    STRICT.store(strict, Ordering::Relaxed);
+}
+
+/// Returns whether strict mode is on, see set_strict()
+pub fn is_strict() -> bool {
+    STRICT.load(Ordering::Relaxed)
+}
+
+/// Reports a value of type T that was dropped without being closed, panicking in strict mode
+///
+/// Without panicking, the message is passed to the handler of set_drop_close_error_handler(), or
+/// printed to stderr if no handler is set.
+pub(crate) fn report_unclosed<T: ?Sized>(message: String, panic: bool) {
+    if (panic || is_strict()) && !std::thread::panicking() {
+        panic!("{}", message);
+    }
+    if !crate::hook::has_handler() {
+        eprintln!("{}", message);
+        return;
+    }
+    let error = CloseError::detached(io::Error::other(message), CloseStage::Close);
+    crate::hook::report_drop_error::<T>(error);
+}
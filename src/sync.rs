@@ -0,0 +1,283 @@
+use crate::{imp, Closable, CloseError, CloseStage, FileSystem};
+use std::{fs, io};
+
+/// How durable the file content should be when it is closed
+///
+/// The levels map to the following system calls:
+///
+/// | Level    | Linux and other unix | Apple platforms       | Windows          |
+/// |----------|----------------------|-----------------------|------------------|
+/// | None     | -                    | -                     | -                |
+/// | DataSync | fdatasync(2)         | fcntl(F_FULLFSYNC)    | FlushFileBuffers |
+/// | Sync     | fsync(2)             | fsync(2)              | FlushFileBuffers |
+/// | Barrier  | fsync(2)             | fcntl(F_BARRIERFSYNC) | FlushFileBuffers |
+/// | FullSync | fsync(2)             | fcntl(F_FULLFSYNC)    | FlushFileBuffers |
+///
+/// On Apple platforms fsync(2) doesn't flush the drive cache, so Sync doesn't protect against
+/// power failure there. File systems that don't support the fcntl calls, like some network file
+/// systems, fall back to fsync(2).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize),
+    serde(rename_all = "snake_case")
+)]
+#[non_exhaustive]
+pub enum Durability {
+    /// Don't sync, only close the file
+    None,
+    /// Sync the file content, but only the metadata needed to read it back
+    ///
+    /// Metadata like the modification time is not synced, which can be a lot faster e.g. for
+    /// append-only files on ext4 and xfs.
+    DataSync,
+    /// Sync file content and metadata with fsync(2)
+    Sync,
+    /// Order the writes before and after the sync, without waiting for the drive cache
+    ///
+    /// Apple recommends this for most apps, as it is enough for e.g. write-ahead logs and much
+    /// cheaper than F_FULLFSYNC.
+    Barrier,
+    /// Sync file content and metadata and flush the drive cache
+    FullSync,
+}
+
+/// The file level steps of closing a file
+///
+/// The steps are applied in a fixed order: writeback is started, the file is synced, its pages
+/// are dropped from the page cache, then it is closed. The defaults are to only close.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct CloseOptions {
+    pub(crate) durability: Durability,
+    pub(crate) auto_durability: bool,
+    pub(crate) start_writeback: bool,
+    pub(crate) drop_cache: bool,
+}
+
+impl CloseOptions {
+    /// Creates options that only close the file
+    pub fn new() -> Self {
+        CloseOptions {
+            durability: Durability::None,
+            auto_durability: false,
+            start_writeback: false,
+            drop_cache: false,
+        }
+    }
+
+    /// Sets the durability level the file is synced with
+    pub fn durability(mut self, durability: Durability) -> Self {
+        self.durability = durability;
+        self
+    }
+
+    /// Sets whether to choose the durability level by the file system of the file
+    ///
+    /// The level is FileSystem::recommended_durability() of the detected file system. If the file
+    /// system can't be detected, the level set with durability() is used.
+    pub fn auto_durability(mut self, auto_durability: bool) -> Self {
+        self.auto_durability = auto_durability;
+        self
+    }
+
+    /// Sets whether to start writing back dirty pages of the file before syncing and closing
+    ///
+    /// This uses sync_file_range(2) on Linux and is a no-op elsewhere. Without syncing, the data
+    /// is written back soon after the close without blocking for it, instead of whenever the
+    /// kernel decides to. Failing to start the writeback doesn't fail the close. See also
+    /// start_writeback().
+    pub fn start_writeback(mut self, start_writeback: bool) -> Self {
+        self.start_writeback = start_writeback;
+        self
+    }
+
+    /// Sets whether to drop the pages of the file from the page cache after syncing
+    ///
+    /// This uses posix_fadvise(POSIX_FADV_DONTNEED) on Linux and is a no-op elsewhere. It keeps
+    /// files that are written once and not read back from pushing other data out of the cache.
+    /// Dirty pages are not dropped, so this should be combined with a durability level other than
+    /// Durability::None. Failing to drop the pages doesn't fail the close.
+    pub fn drop_cache(mut self, drop_cache: bool) -> Self {
+        self.drop_cache = drop_cache;
+        self
+    }
+}
+
+impl Default for CloseOptions {
+    fn default() -> Self {
+        CloseOptions::new()
+    }
+}
+
+/// Closing with syncing the file content to the storage device first
+///
+/// A successful close() only means that the OS accepted the data, it may still be lost on a crash
+/// or power failure. Syncing makes the data durable and reports write-back errors that close()
+/// would not report on most local file systems.
+pub trait SyncClosable: Closable {
+    /// Applies the given options, then closes the file
+    ///
+    /// Pipes, sockets and character devices like ttys are not synced, as they don't support it.
+    ///
+    /// If a step before closing fails, the file is not closed and the returned error has the
+    /// stage CloseStage::Sync and owns the file, see CloseError::into_file().
+    #[track_caller]
+    fn close_with_options(self, options: &CloseOptions) -> Result<(), CloseError>;
+
+    /// Syncs the file with the given durability level, then closes the file
+    ///
+    /// This is the same as close_with_options() with only the durability level set.
+    #[track_caller]
+    fn close_with(self, durability: Durability) -> Result<(), CloseError>
+    where
+        Self: Sized,
+    {
+        self.close_with_options(&CloseOptions::new().durability(durability))
+    }
+
+    /// Syncs file content and metadata to the storage device, then closes the file
+    ///
+    /// On Apple platforms this uses fcntl(F_FULLFSYNC), as fsync(2) doesn't flush the drive cache
+    /// there. Use close_fsynced() to opt out.
+    ///
+    /// This is the same as close_with(Durability::FullSync).
+    #[track_caller]
+    fn close_synced(self) -> Result<(), CloseError>
+    where
+        Self: Sized,
+    {
+        self.close_with(Durability::FullSync)
+    }
+
+    /// Syncs the file content to the storage device, then closes the file
+    ///
+    /// Unlike close_synced() metadata that is not needed to read the data back, like the
+    /// modification time, is not synced. This uses fdatasync on unix, which can be a lot faster
+    /// than fsync e.g. for append-only files on ext4 and xfs, and FlushFileBuffers on windows. On
+    /// Apple platforms fcntl(F_FULLFSYNC) is used, like for close_synced().
+    ///
+    /// This is the same as close_with(Durability::DataSync).
+    #[track_caller]
+    fn close_datasynced(self) -> Result<(), CloseError>
+    where
+        Self: Sized,
+    {
+        self.close_with(Durability::DataSync)
+    }
+
+    /// Syncs with a plain fsync(2), then closes the file
+    ///
+    /// This is the same as close_synced(), except on Apple platforms where the drive cache is not
+    /// flushed. Data may be lost on power failure, but syncing is a lot faster.
+    ///
+    /// This is the same as close_with(Durability::Sync).
+    #[track_caller]
+    fn close_fsynced(self) -> Result<(), CloseError>
+    where
+        Self: Sized,
+    {
+        self.close_with(Durability::Sync)
+    }
+
+    /// Syncs with an I/O barrier, then closes the file
+    ///
+    /// On Apple platforms this uses fcntl(F_BARRIERFSYNC), which Apple recommends for most apps:
+    /// it orders the writes before and after the barrier, which is enough for e.g. write-ahead
+    /// logs, and is much cheaper than F_FULLFSYNC. Elsewhere this is the same as close_fsynced().
+    ///
+    /// This is the same as close_with(Durability::Barrier).
+    #[track_caller]
+    fn close_barrier_synced(self) -> Result<(), CloseError>
+    where
+        Self: Sized,
+    {
+        self.close_with(Durability::Barrier)
+    }
+}
+
+impl SyncClosable for fs::File {
+    #[track_caller]
+    fn close_with_options(self, options: &CloseOptions) -> Result<(), CloseError> {
+        close_file_with_options(self, options).map(|_| ())
+    }
+}
+
+/// Closes the file like close_with_options() and returns the durability level that was applied
+#[track_caller]
+pub(crate) fn close_file_with_options(
+    file: fs::File,
+    options: &CloseOptions,
+) -> Result<Durability, CloseError> {
+    let (file, durability) = sync_file_with_options(file, options)?;
+    file.close()?;
+    Ok(durability)
+}
+
+/// Applies the steps of the options before closing and returns the still open file
+#[track_caller]
+pub(crate) fn sync_file_with_options(
+    file: fs::File,
+    options: &CloseOptions,
+) -> Result<(fs::File, Durability), CloseError> {
+    let mut durability = Durability::None;
+    if imp::is_syncable(&file) {
+        let mut result = Ok(());
+        if options.start_writeback {
+            // Only a hint, the data is written back later or by the sync below anyway
+            let _ = imp::start_writeback(&file, 0, 0);
+        }
+        durability = options.durability;
+        if options.auto_durability {
+            if let Ok(filesystem) = FileSystem::of(&file) {
+                durability = filesystem.recommended_durability();
+            }
+        }
+        if durability != Durability::None {
+            result = imp::sync(&file, durability);
+        }
+        if result.is_ok() && options.drop_cache {
+            // Only a hint, the data is already written
+            let _ = imp::drop_cache(&file);
+        }
+        if let Err(e) = result {
+            return Err(CloseError::unclosed(e, file, CloseStage::Sync));
+        }
+    }
+    Ok((file, durability))
+}
+
+/// Starts writing back dirty pages in the given range of the file, without waiting for it
+///
+/// A len of 0 means until the end of the file. This uses sync_file_range(2) on Linux and is a
+/// no-op elsewhere. The data is not durable afterwards, no metadata is written and the drive cache
+/// is not flushed, this only spreads the write back over time.
+///
+/// Calling this for each chunk of a large file while writing it, and wait_writeback() for the
+/// chunk before, keeps the amount of dirty pages small, so the final sync at close is fast:
+///
+/// ```no_run
+/// # use std::io::Write;
+/// use close_file::{start_writeback, wait_writeback, SyncClosable};
+///
+/// const CHUNK: u64 = 8 << 20;
+/// let mut f = std::fs::File::create("big").unwrap();
+/// for i in 0..1024 {
+///     f.write_all(&[0; CHUNK as usize]).unwrap();
+///     start_writeback(&f, i * CHUNK, CHUNK).unwrap();
+///     if i > 0 {
+///         wait_writeback(&f, (i - 1) * CHUNK, CHUNK).unwrap();
+///     }
+/// }
+/// f.close_synced().unwrap();
+/// ```
+pub fn start_writeback(file: &fs::File, offset: u64, len: u64) -> io::Result<()> {
+    imp::start_writeback(file, offset, len)
+}
+
+/// Waits until dirty pages in the given range of the file are written back
+///
+/// Pages that were not written back yet are written first. Like start_writeback(), this uses
+/// sync_file_range(2) on Linux and is a no-op elsewhere.
+pub fn wait_writeback(file: &fs::File, offset: u64, len: u64) -> io::Result<()> {
+    imp::wait_writeback(file, offset, len)
+}
@@ -0,0 +1,194 @@
+use crate::{imp, Closable, CloseError, CloseOptions, Durability, SyncClosable};
+use std::borrow::Borrow;
+use std::fs;
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+/// A writer that syncs the file every N bytes or every interval
+///
+/// Bounds how much data a long-running appender, like a log, loses on a crash. The sync happens
+/// at the start of the write after the limit was reached, so a failed sync is returned before
+/// anything of that write is written. Closing syncs the rest and closes the file, with errors of
+/// the stage CloseStage::Sync or CloseStage::Close.
+///
+/// ```
+/// use close_file::{Closable, SyncingWriter};
+/// use std::io::Write;
+/// use std::time::Duration;
+///
+/// let f = std::fs::File::create("temp").unwrap();
+/// let mut log = SyncingWriter::new(f)
+///     .every_bytes(64 << 10)
+///     .every(Duration::from_secs(1));
+/// writeln!(log, "Hello, world!").unwrap();
+/// log.close().unwrap();
+/// ```
+#[derive(Debug)]
+pub struct SyncingWriter {
+    inner: fs::File,
+    durability: Durability,
+    every_bytes: Option<u64>,
+    every: Option<Duration>,
+    unsynced: u64,
+    last_sync: Instant,
+}
+
+impl SyncingWriter {
+    /// Wraps a file that is only synced when it is closed, with Durability::DataSync
+    pub fn new(inner: fs::File) -> Self {
+        SyncingWriter {
+            inner,
+            durability: Durability::DataSync,
+            every_bytes: None,
+            every: None,
+            unsynced: 0,
+            last_sync: Instant::now(),
+        }
+    }
+
+    /// Sets the durability level of the syncs
+    pub fn durability(mut self, durability: Durability) -> Self {
+        self.durability = durability;
+        self
+    }
+
+    /// Syncs once the given number of bytes were written since the last sync
+    pub fn every_bytes(mut self, bytes: u64) -> Self {
+        self.every_bytes = Some(bytes);
+        self
+    }
+
+    /// Syncs once the given time passed since the last sync, if anything was written
+    pub fn every(mut self, interval: Duration) -> Self {
+        self.every = Some(interval);
+        self
+    }
+
+    /// The number of bytes written since the last sync
+    pub fn unsynced(&self) -> u64 {
+        self.unsynced
+    }
+
+    /// Syncs the file now if anything was written since the last sync
+    pub fn sync(&mut self) -> io::Result<()> {
+        if self.unsynced > 0 && imp::is_syncable(&self.inner) {
+            imp::sync(&self.inner, self.durability)?;
+        }
+        self.unsynced = 0;
+        self.last_sync = Instant::now();
+        Ok(())
+    }
+
+    /// Converts the writer into the file, without syncing it
+    pub fn into_inner(self) -> fs::File {
+        self.inner
+    }
+
+    fn is_due(&self) -> bool {
+        self.unsynced > 0
+            && (self.every_bytes.is_some_and(|bytes| self.unsynced >= bytes)
+                || self
+                    .every
+                    .is_some_and(|interval| self.last_sync.elapsed() >= interval))
+    }
+}
+
+impl Write for SyncingWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.is_due() {
+            self.sync()?;
+        }
+        let written = self.inner.write(buf)?;
+        self.unsynced += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Syncs what was written since the last sync, then closes the file
+impl Closable for SyncingWriter {
+    #[track_caller]
+    fn close(self) -> Result<(), CloseError> {
+        if self.unsynced > 0 {
+            self.inner.close_with(self.durability)
+        } else {
+            self.inner.close()
+        }
+    }
+}
+
+impl SyncClosable for SyncingWriter {
+    #[track_caller]
+    fn close_with_options(self, options: &CloseOptions) -> Result<(), CloseError> {
+        self.inner.close_with_options(options)
+    }
+}
+
+/// A writer that syncs the file after every write
+///
+/// Each write only returns once the written data is durable, as an alternative to opening the file
+/// with DurableOpenOptions::write_through(). If the sync fails, the write returns the error even
+/// though the data was written, it is not durable. The default durability level is
+/// Durability::DataSync.
+///
+/// ```
+/// use close_file::{Closable, WriteThrough};
+/// use std::io::Write;
+///
+/// let mut log = WriteThrough::new(std::fs::File::create("temp").unwrap());
+/// writeln!(log, "Hello, world!").unwrap();
+/// log.close().unwrap();
+/// ```
+#[derive(Debug)]
+pub struct WriteThrough<W> {
+    inner: W,
+    durability: Durability,
+    syncable: bool,
+}
+
+impl<W: Borrow<fs::File>> WriteThrough<W> {
+    /// Wraps a file that is synced with Durability::DataSync after every write
+    pub fn new(inner: W) -> Self {
+        WriteThrough::with_durability(inner, Durability::DataSync)
+    }
+
+    /// Wraps a file that is synced with the given durability level after every write
+    pub fn with_durability(inner: W, durability: Durability) -> Self {
+        let syncable = imp::is_syncable(inner.borrow());
+        WriteThrough {
+            inner,
+            durability,
+            syncable,
+        }
+    }
+
+    /// Returns the wrapped file
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: Write + Borrow<fs::File>> Write for WriteThrough<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        if written > 0 && self.syncable && self.durability != Durability::None {
+            imp::sync(self.inner.borrow(), self.durability)?;
+        }
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Closes the file, the data is already synced
+impl<W: Closable> Closable for WriteThrough<W> {
+    #[track_caller]
+    fn close(self) -> Result<(), CloseError> {
+        self.inner.close()
+    }
+}
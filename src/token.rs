@@ -0,0 +1,164 @@
+use crate::sync::{close_file_with_options, sync_file_with_options};
+use crate::{Closable, CloseError, CloseOptions, CloseStage, SyncClosable};
+use std::fs;
+use std::time::SystemTime;
+
+/// Evidence of a successful close, for manifests and audit logs
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct CloseToken {
+    closed_at: SystemTime,
+    durability: crate::Durability,
+    len: Option<u64>,
+}
+
+impl CloseToken {
+    /// When the file was closed
+    pub fn closed_at(&self) -> SystemTime {
+        self.closed_at
+    }
+
+    /// The durability level that was applied before closing
+    ///
+    /// This is Durability::None for files that can't be synced, like pipes, and otherwise the
+    /// level chosen by the options, including CloseOptions::auto_durability().
+    pub fn durability(&self) -> crate::Durability {
+        self.durability
+    }
+
+    /// The size of the file when it was closed, None if it isn't a regular file
+    pub fn bytes(&self) -> Option<u64> {
+        self.len
+    }
+}
+
+/// The size and modification time of a file when it was closed
+///
+/// The metadata is read from the open file after syncing and right before closing, so unlike a
+/// stat(2) of the path after closing, it can't describe a file that replaced it in the meantime.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct CloseReceipt {
+    len: u64,
+    modified: Option<SystemTime>,
+    durability: crate::Durability,
+}
+
+impl CloseReceipt {
+    /// The size of the file when it was closed
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Returns true if the file was empty when it was closed
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The modification time of the file, None if the platform doesn't report it
+    pub fn modified(&self) -> Option<SystemTime> {
+        self.modified
+    }
+
+    /// The durability level that was applied before closing, like CloseToken::durability()
+    pub fn durability(&self) -> crate::Durability {
+        self.durability
+    }
+}
+
+/// Closing that returns a CloseToken or CloseReceipt instead of ()
+///
+/// ```
+/// use close_file::{CloseOptions, Durability, TokenClosable};
+/// use std::io::Write;
+///
+/// let mut f = std::fs::File::create("temp").unwrap();
+/// f.write_all(b"Hello, world!").unwrap();
+/// let token = f.close_with_token(&CloseOptions::new().durability(Durability::Sync)).unwrap();
+/// assert_eq!(token.bytes(), Some(13));
+/// ```
+pub trait TokenClosable: SyncClosable {
+    /// Applies the given options, closes the file and returns what was achieved
+    ///
+    /// Errors are the same as for close_with_options().
+    #[track_caller]
+    fn close_with_token(self, options: &CloseOptions) -> Result<CloseToken, CloseError>;
+
+    /// Applies the given options, closes the file and returns its final metadata
+    ///
+    /// If reading the metadata fails, the file is not closed and the returned error has the stage
+    /// CloseStage::Sync and owns the file. Other errors are the same as for close_with_options().
+    ///
+    /// ```
+    /// use close_file::{CloseOptions, Durability, TokenClosable};
+    /// use std::io::Write;
+    ///
+    /// let mut f = std::fs::File::create("temp").unwrap();
+    /// f.write_all(b"Hello, world!").unwrap();
+    /// let receipt = f
+    ///     .close_with_metadata(&CloseOptions::new().durability(Durability::Sync))
+    ///     .unwrap();
+    /// assert_eq!(receipt.len(), 13);
+    /// ```
+    #[track_caller]
+    fn close_with_metadata(self, options: &CloseOptions) -> Result<CloseReceipt, CloseError>;
+}
+
+impl TokenClosable for fs::File {
+    #[track_caller]
+    fn close_with_token(self, options: &CloseOptions) -> Result<CloseToken, CloseError> {
+        let len = match self.metadata() {
+            Ok(metadata) if metadata.is_file() => Some(metadata.len()),
+            _ => None,
+        };
+        let durability = close_file_with_options(self, options)?;
+        Ok(CloseToken {
+            closed_at: SystemTime::now(),
+            durability,
+            len,
+        })
+    }
+
+    #[track_caller]
+    fn close_with_metadata(self, options: &CloseOptions) -> Result<CloseReceipt, CloseError> {
+        let (file, durability) = sync_file_with_options(self, options)?;
+        let metadata = match file.metadata() {
+            Ok(metadata) => metadata,
+            Err(e) => return Err(CloseError::unclosed(e, file, CloseStage::Sync)),
+        };
+        file.close()?;
+        Ok(CloseReceipt {
+            len: metadata.len(),
+            modified: metadata.modified().ok(),
+            durability,
+        })
+    }
+}
+
+impl TokenClosable for crate::fs::File {
+    #[track_caller]
+    fn close_with_token(self, options: &CloseOptions) -> Result<CloseToken, CloseError> {
+        let (inner, path, on_failure) = self.into_parts();
+        match inner.close_with_token(options) {
+            Ok(token) => Ok(token),
+            Err(e) => {
+                let mut e = e.with_path(path);
+                on_failure.apply(&mut e);
+                Err(e)
+            }
+        }
+    }
+
+    #[track_caller]
+    fn close_with_metadata(self, options: &CloseOptions) -> Result<CloseReceipt, CloseError> {
+        let (inner, path, on_failure) = self.into_parts();
+        match inner.close_with_metadata(options) {
+            Ok(receipt) => Ok(receipt),
+            Err(e) => {
+                let mut e = e.with_path(path);
+                on_failure.apply(&mut e);
+                Err(e)
+            }
+        }
+    }
+}
@@ -0,0 +1,210 @@
+//! Integration with tokio
+//!
+//! tokio::fs::File closes its std file on the blocking thread pool when dropped, and errors of
+//! closing it are discarded. AsyncWriteExt::shutdown() only flushes files and only shuts down the
+//! write side of sockets.
+//!
+//! OBS: This module is only available with the `tokio` feature
+
+use crate::sync::close_file_with_options;
+use crate::{imp, AsyncClosable, Closable, CloseError, CloseOptions, CloseStage, GracefulClose};
+use ::tokio::io::{AsyncReadExt, AsyncWriteExt};
+use ::tokio::net::TcpStream;
+use std::future::Future;
+use std::io;
+use std::panic::{self, Location};
+use std::time::Duration;
+
+/// Syncing before closing, the async counterpart of SyncClosable
+///
+/// ```no_run
+/// use close_file::tokio::FileExt;
+/// use close_file::{CloseOptions, Durability};
+/// use tokio::io::AsyncWriteExt;
+///
+/// # async fn write() {
+/// let mut f = tokio::fs::File::create("temp").await.unwrap();
+/// f.write_all(b"Hello, world!").await.unwrap();
+/// f.close_with_options(&CloseOptions::new().durability(Durability::Sync))
+///     .await
+///     .unwrap();
+/// # }
+/// ```
+pub trait FileExt {
+    /// Flushes the file, then applies the options and closes it on the blocking thread pool
+    ///
+    /// Errors are the same as for SyncClosable::close_with_options(). If the flush fails, the file
+    /// is closed anyway, discarding errors closing it, and the error has the stage
+    /// CloseStage::Flush.
+    #[track_caller]
+    fn close_with_options(
+        self,
+        options: &CloseOptions,
+    ) -> impl Future<Output = Result<(), CloseError>> + Send + use<Self>;
+}
+
+impl FileExt for ::tokio::fs::File {
+    #[track_caller]
+    fn close_with_options(
+        mut self,
+        options: &CloseOptions,
+    ) -> impl Future<Output = Result<(), CloseError>> + Send + use<> {
+        let location = Location::caller();
+        let options = *options;
+        async move {
+            // Waits for the write that is still in flight, reporting its error
+            let flushed = self.flush().await;
+            let file = self.into_std().await;
+            let result = match flushed {
+                Ok(()) => {
+                    unblock(move || close_file_with_options(file, &options).map(|_| ())).await
+                }
+                Err(e) => {
+                    let _ = unblock(move || file.close()).await;
+                    Err(CloseError::detached(e, CloseStage::Flush))
+                }
+            };
+            result.map_err(|mut e| {
+                e.set_location(location);
+                e
+            })
+        }
+    }
+}
+
+/// Flushes the file, then closes it on the blocking thread pool
+///
+/// This is the same as FileExt::close_with_options() with the default options.
+impl AsyncClosable for ::tokio::fs::File {
+    #[track_caller]
+    fn close(self) -> impl Future<Output = Result<(), CloseError>> + Send {
+        self.close_with_options(&CloseOptions::new())
+    }
+}
+
+/// Graceful closing of TCP connections, the async counterpart of GracefulClose::close()
+///
+/// ```no_run
+/// use close_file::tokio::TcpStreamExt;
+/// use close_file::GracefulClose;
+/// use std::time::Duration;
+///
+/// # async fn close() {
+/// let stream = tokio::net::TcpStream::connect("127.0.0.1:8080").await.unwrap();
+/// stream
+///     .close_gracefully(&GracefulClose::new(Duration::from_secs(5)))
+///     .await
+///     .unwrap();
+/// # }
+/// ```
+pub trait TcpStreamExt {
+    /// Shuts down the write side, drains the read side, applies SO_LINGER and closes the stream
+    ///
+    /// The steps and errors are the same as for GracefulClose::close(). The socket is closed on the
+    /// blocking thread pool, as closing waits for unsent data with SO_LINGER set.
+    #[track_caller]
+    fn close_gracefully(
+        self,
+        options: &GracefulClose,
+    ) -> impl Future<Output = Result<(), CloseError>> + Send + use<Self>;
+}
+
+impl TcpStreamExt for TcpStream {
+    #[track_caller]
+    fn close_gracefully(
+        self,
+        options: &GracefulClose,
+    ) -> impl Future<Output = Result<(), CloseError>> + Send + use<> {
+        let location = Location::caller();
+        let options = *options;
+        async move {
+            close_gracefully(self, &options).await.map_err(|mut e| {
+                e.set_location(location);
+                e
+            })
+        }
+    }
+}
+
+/// Shuts down the write side of the stream, then closes it
+///
+/// This is the same as TcpStreamExt::close_gracefully() without draining.
+impl AsyncClosable for TcpStream {
+    #[track_caller]
+    fn close(self) -> impl Future<Output = Result<(), CloseError>> + Send {
+        self.close_gracefully(&GracefulClose::new(Duration::ZERO).drain(false))
+    }
+}
+
+/// Closes the stream gracefully, draining for at most the timeout
+///
+/// See TcpStreamExt::close_gracefully().
+#[track_caller]
+pub fn graceful_close(
+    stream: TcpStream,
+    timeout: Duration,
+) -> impl Future<Output = Result<(), CloseError>> + Send {
+    stream.close_gracefully(&GracefulClose::new(timeout))
+}
+
+/// Applies the steps of GracefulClose::close() to the stream
+async fn close_gracefully(
+    mut stream: TcpStream,
+    options: &GracefulClose,
+) -> Result<(), CloseError> {
+    if let Err(e) = stream.shutdown().await {
+        return close_after(stream, e, CloseStage::Shutdown);
+    }
+    if options.drain {
+        if let Err(e) = drain(&mut stream, options.timeout).await {
+            return close_after(stream, e, CloseStage::Drain);
+        }
+    }
+    let stream = match stream.into_std() {
+        Ok(stream) => stream,
+        Err(e) => return Err(CloseError::detached(e, CloseStage::Close)),
+    };
+    if let Some(linger) = options.linger {
+        // Closing a non-blocking socket doesn't wait for unsent data on all platforms
+        let result = stream.set_nonblocking(false);
+        if let Err(e) = result.and_then(|()| imp::set_linger(&stream, linger)) {
+            let _ = stream.close();
+            return Err(CloseError::detached(e, CloseStage::Linger));
+        }
+    }
+    unblock(move || stream.close()).await
+}
+
+/// Reads and discards data until the end of the stream or the timeout
+async fn drain(stream: &mut TcpStream, timeout: Duration) -> io::Result<()> {
+    let mut buf = [0u8; 4096];
+    let read_to_end = async {
+        while stream.read(&mut buf).await? != 0 {}
+        Ok(())
+    };
+    match ::tokio::time::timeout(timeout, read_to_end).await {
+        Ok(result) => result,
+        Err(_) => Err(io::ErrorKind::TimedOut.into()),
+    }
+}
+
+/// Closes the stream after a failed step, reporting the error of the step
+fn close_after(stream: TcpStream, e: io::Error, stage: CloseStage) -> Result<(), CloseError> {
+    if let Ok(stream) = stream.into_std() {
+        let _ = stream.close();
+    }
+    Err(CloseError::detached(e, stage))
+}
+
+/// Runs the close on the blocking thread pool, resuming panics of it
+async fn unblock<F>(close: F) -> Result<(), CloseError>
+where
+    F: FnOnce() -> Result<(), CloseError> + Send + 'static,
+{
+    match ::tokio::task::spawn_blocking(close).await {
+        Ok(result) => result,
+        Err(e) if e.is_panic() => panic::resume_unwind(e.into_panic()),
+        // The runtime is shutting down, the file was dropped without being closed
+        Err(e) => Err(CloseError::detached(io::Error::other(e), CloseStage::Close)),
+    }
+}
@@ -0,0 +1,165 @@
+use crate::{Closable, CloseError, CloseOptions, SyncClosable};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Read, Seek, Write};
+use std::ops::{Deref, DerefMut};
+use std::panic::Location;
+use std::path::{Path, PathBuf};
+
+/// A file that remembers its path and where it was opened
+///
+/// Close errors carry both, see CloseError::path() and CloseError::opened_at(), so a file that
+/// fails to close among hundreds of others can be traced back to the code that opened it.
+///
+/// Dropping it without closing it panics in strict mode, see set_strict(), otherwise the file is
+/// closed and errors are passed to the handler of set_drop_close_error_handler().
+///
+/// ```
+/// use close_file::{Closable, TrackedFile};
+/// use std::io::Write;
+///
+/// let mut f = TrackedFile::create("temp").unwrap();
+/// f.write_all(b"Hello, world!").unwrap();
+/// f.close().unwrap();
+/// ```
+#[derive(Debug)]
+pub struct TrackedFile {
+    // Only None after closing or into_inner()
+    inner: Option<fs::File>,
+    path: PathBuf,
+    opened_at: &'static Location<'static>,
+}
+
+impl TrackedFile {
+    /// Opens a file in read-only mode, like std::fs::File::open()
+    #[track_caller]
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<TrackedFile> {
+        TrackedFile::open_with(path, OpenOptions::new().read(true))
+    }
+
+    /// Opens a file in write-only mode, like std::fs::File::create()
+    #[track_caller]
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<TrackedFile> {
+        TrackedFile::open_with(
+            path,
+            OpenOptions::new().write(true).create(true).truncate(true),
+        )
+    }
+
+    /// Opens a file with the given options
+    #[track_caller]
+    pub fn open_with<P: AsRef<Path>>(path: P, options: &OpenOptions) -> io::Result<TrackedFile> {
+        let path = path.as_ref();
+        Ok(TrackedFile {
+            inner: Some(options.open(path)?),
+            path: path.to_path_buf(),
+            opened_at: Location::caller(),
+        })
+    }
+
+    /// The path the file was opened with
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Where the file was opened
+    pub fn opened_at(&self) -> &'static Location<'static> {
+        self.opened_at
+    }
+
+    /// Converts the file into a std::fs::File, which doesn't report close errors when dropped
+    pub fn into_inner(mut self) -> fs::File {
+        self.take_file()
+    }
+
+    fn take_file(&mut self) -> fs::File {
+        self.inner.take().expect("the file is only taken once")
+    }
+
+    fn enrich(&mut self, e: CloseError) -> CloseError {
+        let mut e = e.with_path(std::mem::take(&mut self.path));
+        e.set_opened_at(self.opened_at);
+        e
+    }
+}
+
+impl Deref for TrackedFile {
+    type Target = fs::File;
+
+    fn deref(&self) -> &fs::File {
+        self.inner.as_ref().expect("the file is only taken once")
+    }
+}
+
+impl DerefMut for TrackedFile {
+    fn deref_mut(&mut self) -> &mut fs::File {
+        self.inner.as_mut().expect("the file is only taken once")
+    }
+}
+
+impl Read for TrackedFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.deref_mut().read(buf)
+    }
+
+    fn read_to_end(&mut self, buf: &mut Vec<u8>) -> io::Result<usize> {
+        self.deref_mut().read_to_end(buf)
+    }
+}
+
+impl Write for TrackedFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.deref_mut().write(buf)
+    }
+
+    fn write_vectored(&mut self, bufs: &[io::IoSlice<'_>]) -> io::Result<usize> {
+        self.deref_mut().write_vectored(bufs)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.deref_mut().flush()
+    }
+}
+
+impl Seek for TrackedFile {
+    fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+        self.deref_mut().seek(pos)
+    }
+}
+
+impl Closable for TrackedFile {
+    #[track_caller]
+    fn close(mut self) -> Result<(), CloseError> {
+        match self.take_file().close() {
+            Ok(()) => Ok(()),
+            Err(e) => Err(self.enrich(e)),
+        }
+    }
+}
+
+impl SyncClosable for TrackedFile {
+    #[track_caller]
+    fn close_with_options(mut self, options: &CloseOptions) -> Result<(), CloseError> {
+        match self.take_file().close_with_options(options) {
+            Ok(()) => Ok(()),
+            Err(e) => Err(self.enrich(e)),
+        }
+    }
+}
+
+impl Drop for TrackedFile {
+    fn drop(&mut self) {
+        if let Some(inner) = self.inner.take() {
+            let message = format!(
+                "{} opened at {} was dropped without being closed",
+                self.path.display(),
+                self.opened_at
+            );
+            if let Err(e) = inner.close() {
+                crate::hook::report_drop_error::<Self>(self.enrich(e));
+            }
+            if crate::is_strict() {
+                crate::strict::report_unclosed::<Self>(message, true);
+            }
+        }
+    }
+}
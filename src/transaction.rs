@@ -0,0 +1,137 @@
+use crate::atomic::TEMP_COUNTER;
+use crate::{sync_parent_dir, AtomicWriteFile, AtomicWriteOptions, CloseError, CloseStage};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
+use std::{fs, io, mem, process};
+
+/// Several atomic writes that are committed together
+///
+/// The files are written to temporary files in a staging directory. Committing first syncs and
+/// closes all of them, and only if that succeeded for every file, renames them over their targets
+/// in the order they were created and syncs the directories. If any file fails to be synced or
+/// closed, all files are aborted and no target is touched.
+///
+/// A rename can still fail after earlier files were renamed, e.g. when the disk is removed. Create
+/// the file that marks the transaction as complete, like a manifest, last, so it only appears
+/// when all other files are in place.
+///
+/// The staging directory is created in the given directory, which must be on the same file system
+/// as the targets. Dropping the transaction without committing aborts it.
+///
+/// ```
+/// use close_file::FileTransaction;
+/// use std::io::Write;
+///
+/// let dir = std::env::temp_dir();
+/// let mut transaction = FileTransaction::new(&dir).unwrap();
+/// transaction.create(dir.join("close-file-txn-doc-data")).unwrap().write_all(b"data").unwrap();
+/// transaction.create(dir.join("close-file-txn-doc-manifest")).unwrap().write_all(b"1").unwrap();
+/// transaction.commit().unwrap();
+/// # std::fs::remove_file(dir.join("close-file-txn-doc-data")).unwrap();
+/// # std::fs::remove_file(dir.join("close-file-txn-doc-manifest")).unwrap();
+/// ```
+#[derive(Debug)]
+pub struct FileTransaction {
+    staging_dir: PathBuf,
+    files: Vec<AtomicWriteFile>,
+}
+
+impl FileTransaction {
+    /// Starts a transaction, creating its staging directory in the given directory
+    pub fn new<P: AsRef<Path>>(dir: P) -> io::Result<FileTransaction> {
+        loop {
+            let staging_dir = dir.as_ref().join(format!(
+                ".close-file-txn.{}.{}",
+                process::id(),
+                TEMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+            ));
+            match fs::create_dir(&staging_dir) {
+                Ok(()) => {
+                    return Ok(FileTransaction {
+                        staging_dir,
+                        files: Vec::new(),
+                    })
+                }
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// The directory the temporary files are written to
+    pub fn staging_dir(&self) -> &Path {
+        &self.staging_dir
+    }
+
+    /// Adds a file replacing the given path to the transaction
+    pub fn create<P: AsRef<Path>>(&mut self, path: P) -> io::Result<&mut AtomicWriteFile> {
+        self.create_with(path, &AtomicWriteOptions::new())
+    }
+
+    /// Adds a file replacing the given path to the transaction, created with the given options
+    ///
+    /// The temporary file is created in the staging directory regardless of the options.
+    pub fn create_with<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        options: &AtomicWriteOptions,
+    ) -> io::Result<&mut AtomicWriteFile> {
+        let file = options.clone().temp_dir(&self.staging_dir).create(path)?;
+        self.files.push(file);
+        Ok(self.files.last_mut().expect("file was just added"))
+    }
+
+    /// Commits all files, see FileTransaction
+    ///
+    /// Returns the first error. After a rename failed, the files before it are already replaced
+    /// and the files after it are aborted.
+    #[track_caller]
+    pub fn commit(mut self) -> Result<(), CloseError> {
+        let mut files = mem::take(&mut self.files);
+        for file in &mut files {
+            file.prepare()?;
+        }
+        for file in &mut files {
+            file.install()?;
+        }
+        let mut dirs: Vec<&Path> = Vec::new();
+        for file in &files {
+            let dir = file.path().parent().unwrap_or_else(|| Path::new(""));
+            if !dirs.contains(&dir) {
+                dirs.push(dir);
+                if let Err(e) = sync_parent_dir(file.path()) {
+                    return Err(CloseError::detached(e, CloseStage::SyncDir).with_path(file.path()));
+                }
+            }
+        }
+        drop(files);
+        if let Err(e) = fs::remove_dir(&self.staging_dir) {
+            return Err(CloseError::detached(e, CloseStage::Remove).with_path(&self.staging_dir));
+        }
+        Ok(())
+    }
+
+    /// Aborts all files and removes the staging directory, returning the first error
+    #[track_caller]
+    pub fn abort(mut self) -> Result<(), CloseError> {
+        let mut result = Ok(());
+        for file in mem::take(&mut self.files) {
+            let aborted = file.abort();
+            if result.is_ok() {
+                result = aborted;
+            }
+        }
+        if let Err(e) = fs::remove_dir(&self.staging_dir) {
+            result?;
+            return Err(CloseError::detached(e, CloseStage::Remove).with_path(&self.staging_dir));
+        }
+        result
+    }
+}
+
+impl Drop for FileTransaction {
+    fn drop(&mut self) {
+        self.files.clear();
+        let _ = fs::remove_dir(&self.staging_dir);
+    }
+}
@@ -0,0 +1,102 @@
+use crate::{Closable, CloseError};
+use std::{error, fmt, fs, io};
+
+/// Closing that hands the resource back if it is still usable after a failure
+///
+/// Unlike CloseError::into_file(), the resource is returned as it is, e.g. a BufWriter with its
+/// buffer, so the caller can sync, seek or rewrite it before retrying.
+///
+/// ```
+/// use close_file::{TryCloseError, TryClosable};
+///
+/// let f = std::fs::File::create("temp").unwrap();
+/// match f.try_close() {
+///     Ok(()) => {}
+///     Err(TryCloseError::Retained(f, e)) => {
+///         eprintln!("closing failed, retrying: {}", e);
+///         f.try_close().unwrap();
+///     }
+///     Err(TryCloseError::Closed(e)) => panic!("closing failed: {}", e),
+/// }
+/// ```
+pub trait TryClosable: Sized {
+    #[track_caller]
+    fn try_close(self) -> Result<(), TryCloseError<Self>>;
+}
+
+/// The error of TryClosable::try_close(), by whether the resource is still usable
+#[derive(Debug)]
+pub enum TryCloseError<T> {
+    /// Closing failed before the resource was released, so it is handed back
+    Retained(T, io::Error),
+    /// The resource was released, so closing can't be retried
+    Closed(CloseError),
+}
+
+impl<T> TryCloseError<T> {
+    /// Returns the underlying I/O error
+    pub fn as_io_error(&self) -> &io::Error {
+        match self {
+            TryCloseError::Retained(_, e) => e,
+            TryCloseError::Closed(e) => e.as_io_error(),
+        }
+    }
+
+    /// Consumes the error, returning the resource if it is still usable
+    pub fn into_resource(self) -> Option<T> {
+        match self {
+            TryCloseError::Retained(resource, _) => Some(resource),
+            TryCloseError::Closed(_) => None,
+        }
+    }
+
+    /// Converts the resource of a retained error, e.g. to wrap it again
+    pub fn map<U, F: FnOnce(T) -> U>(self, f: F) -> TryCloseError<U> {
+        match self {
+            TryCloseError::Retained(resource, e) => TryCloseError::Retained(f(resource), e),
+            TryCloseError::Closed(e) => TryCloseError::Closed(e),
+        }
+    }
+}
+
+impl<T: fmt::Debug> error::Error for TryCloseError<T> {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            TryCloseError::Retained(_, e) => Some(e),
+            TryCloseError::Closed(e) => Some(e),
+        }
+    }
+}
+
+impl<T> fmt::Display for TryCloseError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TryCloseError::Retained(_, e) => write!(f, "failed to close, still open: {}", e),
+            TryCloseError::Closed(e) => fmt::Display::fmt(e, f),
+        }
+    }
+}
+
+/// Converts the error into an io::Error, dropping a retained resource
+impl<T> From<TryCloseError<T>> for io::Error {
+    fn from(err: TryCloseError<T>) -> io::Error {
+        match err {
+            TryCloseError::Retained(_, e) => e,
+            TryCloseError::Closed(e) => e.into(),
+        }
+    }
+}
+
+/// Hands the file back if the platform left it open after the failed close
+impl TryClosable for fs::File {
+    #[track_caller]
+    fn try_close(self) -> Result<(), TryCloseError<Self>> {
+        match self.close() {
+            Ok(()) => Ok(()),
+            Err(mut e) => match e.take_file() {
+                Some(file) => Err(TryCloseError::Retained(file, e.into_io_error())),
+                None => Err(TryCloseError::Closed(e)),
+            },
+        }
+    }
+}
@@ -0,0 +1,601 @@
+use crate::{CloseError, CloseErrorKind, Durability, FileSystem, RemoteFailure};
+use std::os::unix::prelude::*;
+use std::{fs, io};
+
+pub(crate) fn kind(code: i32) -> CloseErrorKind {
+    match code {
+        libc::EINTR => CloseErrorKind::Interrupted,
+        libc::ENOSPC => CloseErrorKind::OutOfSpace,
+        libc::EDQUOT => CloseErrorKind::QuotaExceeded,
+        libc::EIO => CloseErrorKind::IoFailure,
+        libc::EBADF => CloseErrorKind::InvalidDescriptor,
+        libc::ECONNRESET => CloseErrorKind::ConnectionReset,
+        libc::ENOTCONN => CloseErrorKind::NotConnected,
+        _ => CloseErrorKind::Other,
+    }
+}
+
+pub(crate) fn remote_failure(code: i32) -> Option<RemoteFailure> {
+    match code {
+        libc::ESTALE => Some(RemoteFailure::StaleHandle),
+        libc::ETIMEDOUT
+        | libc::EHOSTDOWN
+        | libc::EHOSTUNREACH
+        | libc::ENETDOWN
+        | libc::ENETUNREACH => Some(RemoteFailure::ConnectionLost),
+        libc::EIO => Some(RemoteFailure::DelayedWriteFailed),
+        _ => None,
+    }
+}
+
+/// Whether the file descriptor is still open after close() failed with the given errno
+pub(crate) fn retained_after_close(code: i32) -> bool {
+    cfg!(target_os = "aix") && code == libc::EINTR
+}
+
+/// Calls f until it doesn't fail with EINTR
+fn cvt_r(mut f: impl FnMut() -> libc::c_int) -> io::Result<()> {
+    loop {
+        if f() != -1 {
+            return Ok(());
+        }
+        let err = io::Error::last_os_error();
+        if err.kind() != io::ErrorKind::Interrupted {
+            return Err(err);
+        }
+    }
+}
+
+fn fsync(file: &fs::File) -> io::Result<()> {
+    cvt_r(|| unsafe { libc::fsync(file.as_raw_fd()) })
+}
+
+fn fdatasync(file: &fs::File) -> io::Result<()> {
+    // Uses fdatasync(2) where available, only Apple platforms differ, which don't get here
+    file.sync_data()
+}
+
+/// Syncs the file and flushes the drive cache
+///
+/// On Apple platforms fsync(2) doesn't flush the drive cache, this needs fcntl(F_FULLFSYNC). File
+/// systems that don't support F_FULLFSYNC, like some network file systems, fall back to fsync(2).
+fn full_fsync(file: &fs::File) -> io::Result<()> {
+    #[cfg(target_vendor = "apple")]
+    match cvt_r(|| unsafe { libc::fcntl(file.as_raw_fd(), libc::F_FULLFSYNC) }) {
+        Err(e) if matches!(e.raw_os_error(), Some(libc::ENOTSUP) | Some(libc::EINVAL)) => {}
+        result => return result,
+    }
+    fsync(file)
+}
+
+/// Syncs the file with an I/O barrier
+///
+/// On Apple platforms this uses fcntl(F_BARRIERFSYNC), which makes sure that writes before the
+/// barrier reach the storage before writes after it, without waiting for the drive cache to be
+/// flushed. Elsewhere and for file systems that don't support it, fsync(2) is used.
+fn barrier_fsync(file: &fs::File) -> io::Result<()> {
+    #[cfg(target_vendor = "apple")]
+    match cvt_r(|| unsafe { libc::fcntl(file.as_raw_fd(), libc::F_BARRIERFSYNC) }) {
+        Err(e) if matches!(e.raw_os_error(), Some(libc::ENOTSUP) | Some(libc::EINVAL)) => {}
+        result => return result,
+    }
+    fsync(file)
+}
+
+/// Whether syncing can work for the file, i.e. it is not a pipe, socket or character device
+///
+/// If the file type can't be determined, syncing is attempted to not hide genuine failures.
+pub(crate) fn is_syncable(file: &fs::File) -> bool {
+    match file.metadata() {
+        Ok(metadata) => {
+            let file_type = metadata.file_type();
+            !(file_type.is_fifo() || file_type.is_socket() || file_type.is_char_device())
+        }
+        Err(_) => true,
+    }
+}
+
+pub(crate) fn open_dir(path: &std::path::Path) -> io::Result<fs::File> {
+    fs::OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_DIRECTORY)
+        .open(path)
+}
+
+/// Detects the file system of the path with statfs(2)
+#[cfg(any(
+    target_os = "linux",
+    target_os = "android",
+    target_vendor = "apple",
+    target_os = "freebsd",
+    target_os = "openbsd",
+    target_os = "dragonfly"
+))]
+pub(crate) fn filesystem(path: &std::path::Path) -> io::Result<FileSystem> {
+    let path = std::ffi::CString::new(path.as_os_str().as_bytes())
+        .map_err(|_| io::ErrorKind::InvalidInput)?;
+    let mut stat = std::mem::MaybeUninit::<libc::statfs>::uninit();
+    cvt_r(|| unsafe { libc::statfs(path.as_ptr(), stat.as_mut_ptr()) })?;
+    Ok(filesystem_of(unsafe { &stat.assume_init() }))
+}
+
+/// Detects the file system of the file with fstatfs(2)
+#[cfg(any(
+    target_os = "linux",
+    target_os = "android",
+    target_vendor = "apple",
+    target_os = "freebsd",
+    target_os = "openbsd",
+    target_os = "dragonfly"
+))]
+pub(crate) fn filesystem_of_file(file: &fs::File) -> io::Result<FileSystem> {
+    let mut stat = std::mem::MaybeUninit::<libc::statfs>::uninit();
+    cvt_r(|| unsafe { libc::fstatfs(file.as_raw_fd(), stat.as_mut_ptr()) })?;
+    Ok(filesystem_of(unsafe { &stat.assume_init() }))
+}
+
+// Other platforms like NetBSD and illumos only have statvfs(2), which doesn't name the file system
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "android",
+    target_vendor = "apple",
+    target_os = "freebsd",
+    target_os = "openbsd",
+    target_os = "dragonfly"
+)))]
+pub(crate) fn filesystem(_path: &std::path::Path) -> io::Result<FileSystem> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "android",
+    target_vendor = "apple",
+    target_os = "freebsd",
+    target_os = "openbsd",
+    target_os = "dragonfly"
+)))]
+pub(crate) fn filesystem_of_file(_file: &fs::File) -> io::Result<FileSystem> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn filesystem_of(stat: &libc::statfs) -> FileSystem {
+    // The magic numbers of linux/magic.h, f_type has a different type per architecture
+    match stat.f_type as u32 {
+        0xef53 => FileSystem::Ext,
+        0x5846_5342 => FileSystem::Xfs,
+        0x9123_683e => FileSystem::Btrfs,
+        0x2fc1_2fc1 => FileSystem::Zfs,
+        0xf2f5_2010 => FileSystem::F2fs,
+        0x0102_1994 | 0x8584_58f6 => FileSystem::Tmpfs,
+        0x6969 => FileSystem::Nfs,
+        0xff53_4d42 | 0xfe53_4d42 | 0x517b => FileSystem::Smb,
+        0x6573_5546 => FileSystem::Fuse,
+        0x794c_7630 => FileSystem::Overlay,
+        0x5346_544e => FileSystem::Ntfs,
+        0x4d44 | 0x2011_bab0 => FileSystem::Fat,
+        _ => FileSystem::Unknown,
+    }
+}
+
+#[cfg(any(target_vendor = "apple", target_os = "freebsd"))]
+fn filesystem_of(stat: &libc::statfs) -> FileSystem {
+    let name = unsafe { std::ffi::CStr::from_ptr(stat.f_fstypename.as_ptr()) };
+    FileSystem::from_name(&name.to_string_lossy())
+}
+
+#[cfg(any(target_os = "openbsd", target_os = "dragonfly"))]
+fn filesystem_of(_stat: &libc::statfs) -> FileSystem {
+    FileSystem::Unknown
+}
+
+/// Creates an anonymous file in the directory with O_TMPFILE
+///
+/// Returns None if the platform or file system doesn't support it.
+pub(crate) fn create_anonymous(dir: &std::path::Path) -> io::Result<Option<fs::File>> {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    {
+        let result = fs::OpenOptions::new()
+            .write(true)
+            .custom_flags(libc::O_TMPFILE)
+            .mode(0o666)
+            .open(dir);
+        match result {
+            Ok(file) => Ok(Some(file)),
+            // Kernels without O_TMPFILE see O_DIRECTORY, which is part of it, and fail with EISDIR
+            Err(e)
+                if matches!(
+                    e.raw_os_error(),
+                    Some(libc::EOPNOTSUPP) | Some(libc::EISDIR) | Some(libc::EINVAL)
+                ) =>
+            {
+                Ok(None)
+            }
+            Err(e) => Err(e),
+        }
+    }
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    {
+        let _ = dir;
+        Ok(None)
+    }
+}
+
+/// Links a file created by create_anonymous() to the path
+///
+/// Fails with AlreadyExists if the path exists.
+pub(crate) fn link_anonymous(file: &fs::File, path: &std::path::Path) -> io::Result<()> {
+    let path = std::ffi::CString::new(path.as_os_str().as_bytes())
+        .map_err(|_| io::ErrorKind::InvalidInput)?;
+    // AT_EMPTY_PATH needs CAP_DAC_READ_SEARCH, going through procfs doesn't
+    let source = std::ffi::CString::new(format!("/proc/self/fd/{}", file.as_raw_fd()))
+        .map_err(|_| io::ErrorKind::InvalidInput)?;
+    cvt_r(|| unsafe {
+        libc::linkat(
+            libc::AT_FDCWD,
+            source.as_ptr(),
+            libc::AT_FDCWD,
+            path.as_ptr(),
+            libc::AT_SYMLINK_FOLLOW,
+        )
+    })
+}
+
+/// Renames the replacement over the target, keeping the target at the backup path if given
+///
+/// The backup is a hard link, so the target exists at all times. File systems without hard links
+/// fall back to renaming the target.
+pub(crate) fn replace_file(
+    replacement: &std::path::Path,
+    target: &std::path::Path,
+    backup: Option<&std::path::Path>,
+) -> io::Result<()> {
+    if let Some(backup) = backup {
+        ignore_not_found(fs::remove_file(backup))?;
+        match fs::hard_link(target, backup) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(_) => ignore_not_found(fs::rename(target, backup))?,
+            Ok(()) => {}
+        }
+    }
+    fs::rename(replacement, target)
+}
+
+fn ignore_not_found(result: io::Result<()>) -> io::Result<()> {
+    match result {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
+
+pub(crate) fn set_write_through(options: &mut fs::OpenOptions, durability: Durability) {
+    match durability {
+        Durability::None => {}
+        Durability::DataSync => {
+            options.custom_flags(libc::O_DSYNC);
+        }
+        _ => {
+            options.custom_flags(libc::O_SYNC);
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "solaris", target_os = "illumos")))]
+pub(crate) fn lock(file: &fs::File, exclusive: bool) -> io::Result<()> {
+    let operation = if exclusive {
+        libc::LOCK_EX
+    } else {
+        libc::LOCK_SH
+    };
+    cvt_r(|| unsafe { libc::flock(file.as_raw_fd(), operation) })
+}
+
+#[cfg(not(any(target_os = "solaris", target_os = "illumos")))]
+pub(crate) fn unlock(file: &fs::File) -> io::Result<()> {
+    cvt_r(|| unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_UN) })
+}
+
+// Solaris has no flock(2), so the whole file is locked with fcntl(F_SETLKW) there
+#[cfg(any(target_os = "solaris", target_os = "illumos"))]
+pub(crate) fn lock(file: &fs::File, exclusive: bool) -> io::Result<()> {
+    let lock_type = if exclusive {
+        libc::F_WRLCK
+    } else {
+        libc::F_RDLCK
+    };
+    set_lock(file, lock_type, libc::F_SETLKW)
+}
+
+#[cfg(any(target_os = "solaris", target_os = "illumos"))]
+pub(crate) fn unlock(file: &fs::File) -> io::Result<()> {
+    set_lock(file, libc::F_UNLCK, libc::F_SETLK)
+}
+
+#[cfg(any(target_os = "solaris", target_os = "illumos"))]
+fn set_lock(file: &fs::File, lock_type: libc::c_short, command: libc::c_int) -> io::Result<()> {
+    let mut lock: libc::flock = unsafe { std::mem::zeroed() };
+    lock.l_type = lock_type;
+    lock.l_whence = libc::SEEK_SET as libc::c_short;
+    // A length of 0 covers the whole file, also when it grows
+    lock.l_start = 0;
+    lock.l_len = 0;
+    cvt_r(|| unsafe { libc::fcntl(file.as_raw_fd(), command, &lock) })
+}
+
+pub(crate) fn sync(file: &fs::File, durability: Durability) -> io::Result<()> {
+    match durability {
+        Durability::None => Ok(()),
+        Durability::DataSync if cfg!(target_vendor = "apple") => full_fsync(file),
+        Durability::DataSync => fdatasync(file),
+        Durability::Sync => fsync(file),
+        Durability::Barrier => barrier_fsync(file),
+        Durability::FullSync => full_fsync(file),
+    }
+}
+
+/// Syncs the file systems of the files with one syncfs(2) each
+///
+/// Returns for each file whether it is synced. Non-syncable files count as synced.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub(crate) fn sync_filesystems(files: &[fs::File]) -> Vec<bool> {
+    let mut synced = vec![false; files.len()];
+    let mut devices: Vec<(u64, bool)> = Vec::new();
+    for (i, file) in files.iter().enumerate() {
+        if !is_syncable(file) {
+            synced[i] = true;
+            continue;
+        }
+        let device = match file.metadata() {
+            Ok(metadata) => metadata.dev(),
+            Err(_) => continue,
+        };
+        synced[i] = match devices.iter().find(|(dev, _)| *dev == device) {
+            Some(&(_, ok)) => ok,
+            None => {
+                let ok = cvt_r(|| unsafe { libc::syncfs(file.as_raw_fd()) }).is_ok();
+                devices.push((device, ok));
+                ok
+            }
+        };
+    }
+    synced
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub(crate) fn sync_filesystems(files: &[fs::File]) -> Vec<bool> {
+    vec![false; files.len()]
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn sync_file_range(file: &fs::File, offset: u64, len: u64, flags: libc::c_uint) -> io::Result<()> {
+    use std::convert::TryInto;
+
+    let offset = offset.try_into().map_err(|_| io::ErrorKind::InvalidInput)?;
+    let len = len.try_into().map_err(|_| io::ErrorKind::InvalidInput)?;
+    cvt_r(|| unsafe { libc::sync_file_range(file.as_raw_fd(), offset, len, flags) })
+}
+
+pub(crate) fn start_writeback(file: &fs::File, offset: u64, len: u64) -> io::Result<()> {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    return sync_file_range(file, offset, len, libc::SYNC_FILE_RANGE_WRITE);
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    {
+        let _ = (file, offset, len);
+        Ok(())
+    }
+}
+
+pub(crate) fn wait_writeback(file: &fs::File, offset: u64, len: u64) -> io::Result<()> {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    return sync_file_range(
+        file,
+        offset,
+        len,
+        libc::SYNC_FILE_RANGE_WAIT_BEFORE
+            | libc::SYNC_FILE_RANGE_WRITE
+            | libc::SYNC_FILE_RANGE_WAIT_AFTER,
+    );
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    {
+        let _ = (file, offset, len);
+        Ok(())
+    }
+}
+
+pub(crate) fn drop_cache(file: &fs::File) -> io::Result<()> {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    {
+        // posix_fadvise returns the error instead of setting errno
+        let rc = unsafe { libc::posix_fadvise(file.as_raw_fd(), 0, 0, libc::POSIX_FADV_DONTNEED) };
+        if rc != 0 {
+            return Err(io::Error::from_raw_os_error(rc));
+        }
+    }
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    let _ = file;
+    Ok(())
+}
+
+#[track_caller]
+pub(crate) fn close(fd: OwnedFd) -> Result<(), CloseError> {
+    let fd = fd.into_raw_fd();
+    if unsafe { libc::close(fd) } != 0 {
+        let io_error = io::Error::last_os_error();
+        let owned = io_error
+            .raw_os_error()
+            .filter(|&code| retained_after_close(code))
+            .map(|_| unsafe { OwnedFd::from_raw_fd(fd) });
+        return Err(CloseError::new(io_error, fd, owned));
+    }
+    Ok(())
+}
+
+/// Closes the file of the standard stream fd, which is replaced by /dev/null
+///
+/// Closing the fd itself would let the next opened file take its place and receive all output.
+/// Instead the open file description is kept by a duplicate only, so closing the duplicate reports
+/// the errors of closing the file.
+#[track_caller]
+fn close_stdio(fd: RawFd) -> Result<(), CloseError> {
+    let null = fs::OpenOptions::new()
+        .write(true)
+        .open("/dev/null")
+        .map_err(|e| CloseError::detached(e, crate::CloseStage::Close))?;
+    let duplicate = unsafe { libc::fcntl(fd, libc::F_DUPFD_CLOEXEC, 0) };
+    if duplicate < 0 {
+        let e = io::Error::last_os_error();
+        if e.raw_os_error() == Some(libc::EBADF) {
+            // Already closed
+            return Ok(());
+        }
+        return Err(CloseError::detached(e, crate::CloseStage::Close));
+    }
+    let duplicate = unsafe { OwnedFd::from_raw_fd(duplicate) };
+    if unsafe { libc::dup2(null.as_raw_fd(), fd) } < 0 {
+        return Err(CloseError::detached(
+            io::Error::last_os_error(),
+            crate::CloseStage::Close,
+        ));
+    }
+    close(duplicate)
+}
+
+#[track_caller]
+pub(crate) fn close_stdout() -> Result<(), CloseError> {
+    close_stdio(libc::STDOUT_FILENO)
+}
+
+#[track_caller]
+pub(crate) fn close_stderr() -> Result<(), CloseError> {
+    close_stdio(libc::STDERR_FILENO)
+}
+
+impl crate::Closable for fs::File {
+    #[track_caller]
+    fn close(self) -> Result<(), CloseError> {
+        close(self.into())
+    }
+}
+
+pub(crate) fn set_linger(
+    stream: &std::net::TcpStream,
+    linger: std::time::Duration,
+) -> io::Result<()> {
+    use std::convert::TryInto;
+    let linger = libc::linger {
+        l_onoff: 1,
+        l_linger: crate::net::linger_secs(linger)
+            .try_into()
+            .unwrap_or(libc::c_int::MAX),
+    };
+    let rc = unsafe {
+        libc::setsockopt(
+            stream.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_LINGER,
+            &linger as *const libc::linger as *const libc::c_void,
+            std::mem::size_of::<libc::linger>() as libc::socklen_t,
+        )
+    };
+    if rc != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+impl crate::Closable for std::net::TcpStream {
+    #[track_caller]
+    fn close(self) -> Result<(), CloseError> {
+        close(self.into())
+    }
+}
+
+impl crate::Closable for std::net::TcpListener {
+    #[track_caller]
+    fn close(self) -> Result<(), CloseError> {
+        close(self.into())
+    }
+}
+
+impl crate::Closable for std::net::UdpSocket {
+    #[track_caller]
+    fn close(self) -> Result<(), CloseError> {
+        close(self.into())
+    }
+}
+
+impl crate::Closable for std::os::unix::net::UnixStream {
+    #[track_caller]
+    fn close(self) -> Result<(), CloseError> {
+        close(self.into())
+    }
+}
+
+impl crate::Closable for std::os::unix::net::UnixListener {
+    #[track_caller]
+    fn close(self) -> Result<(), CloseError> {
+        close(self.into())
+    }
+}
+
+impl crate::Closable for std::os::unix::net::UnixDatagram {
+    #[track_caller]
+    fn close(self) -> Result<(), CloseError> {
+        close(self.into())
+    }
+}
+
+/// Closes any kind of file descriptor, e.g. of a socket or pipe or one received from another
+/// process
+impl crate::Closable for OwnedFd {
+    #[track_caller]
+    fn close(self) -> Result<(), CloseError> {
+        close(self)
+    }
+}
+
+/// Syncs like a file, pipes, sockets and character devices are only closed
+impl crate::SyncClosable for OwnedFd {
+    #[track_caller]
+    fn close_with_options(self, options: &crate::CloseOptions) -> Result<(), CloseError> {
+        fs::File::from(self).close_with_options(options)
+    }
+}
+
+/// Closes the pipe to the child, which signals the end of its input
+impl crate::Closable for std::process::ChildStdin {
+    #[track_caller]
+    fn close(self) -> Result<(), CloseError> {
+        close(self.into())
+    }
+}
+
+impl crate::Closable for std::process::ChildStdout {
+    #[track_caller]
+    fn close(self) -> Result<(), CloseError> {
+        close(self.into())
+    }
+}
+
+impl crate::Closable for std::process::ChildStderr {
+    #[track_caller]
+    fn close(self) -> Result<(), CloseError> {
+        close(self.into())
+    }
+}
+
+impl crate::Closable for std::io::PipeReader {
+    #[track_caller]
+    fn close(self) -> Result<(), CloseError> {
+        close(self.into())
+    }
+}
+
+/// Closes the write end of the pipe, which signals the end of the data to the reader
+impl crate::Closable for std::io::PipeWriter {
+    #[track_caller]
+    fn close(self) -> Result<(), CloseError> {
+        close(self.into())
+    }
+}
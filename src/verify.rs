@@ -0,0 +1,217 @@
+use crate::{Closable, CloseError, CloseOptions, CloseStage, SyncClosable};
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::Hasher;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+/// A writer that fails the close if not exactly the expected number of bytes were written
+///
+/// Catches truncated downloads and transfers when the file is finalized. The length is checked
+/// after the writer was closed, a mismatch has the stage CloseStage::Verify.
+///
+/// ```
+/// use close_file::{Closable, ExpectedLen};
+/// use std::io::Write;
+///
+/// let mut w = ExpectedLen::new(std::fs::File::create("temp").unwrap(), 13);
+/// w.write_all(b"Hello, world!").unwrap();
+/// w.close().unwrap();
+/// ```
+#[derive(Debug)]
+pub struct ExpectedLen<W> {
+    inner: W,
+    expected: u64,
+    written: u64,
+}
+
+impl<W> ExpectedLen<W> {
+    /// Wraps a writer that must receive exactly `expected` bytes
+    pub fn new(inner: W, expected: u64) -> Self {
+        ExpectedLen {
+            inner,
+            expected,
+            written: 0,
+        }
+    }
+
+    /// The number of bytes the writer must receive
+    pub fn expected(&self) -> u64 {
+        self.expected
+    }
+
+    /// The number of bytes written so far
+    pub fn written(&self) -> u64 {
+        self.written
+    }
+
+    /// Returns the writer without closing it or checking the length
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: Write> Write for ExpectedLen<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.written += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Closes the writer, then checks the number of bytes written
+impl<W: Closable> Closable for ExpectedLen<W> {
+    #[track_caller]
+    fn close(self) -> Result<(), CloseError> {
+        self.inner.close()?;
+        if self.written != self.expected {
+            return Err(CloseError::detached(
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "expected {} bytes, but {} were written",
+                        self.expected, self.written
+                    ),
+                ),
+                CloseStage::Verify,
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// A writer that reads the file back after closing it and compares it to what was written
+///
+/// For pipelines that can't afford silent corruption, like firmware images and backups. After a
+/// successful close the file at the path is reopened and its length and checksum are compared to
+/// the written data. A mismatch has the kind CloseErrorKind::VerificationFailed, errors reading
+/// the file back have the stage CloseStage::Verify.
+///
+/// The data is usually read back from the page cache, close with CloseOptions::drop_cache() and a
+/// durability level to read it from the storage device on Linux.
+///
+/// ```
+/// use close_file::{Closable, VerifyAfterClose};
+/// use std::io::Write;
+///
+/// let f = std::fs::File::create("temp").unwrap();
+/// let mut w = VerifyAfterClose::new(f, "temp");
+/// w.write_all(b"Hello, world!").unwrap();
+/// w.close().unwrap();
+/// ```
+#[derive(Debug)]
+pub struct VerifyAfterClose<W> {
+    inner: W,
+    written: Written,
+}
+
+/// What was written, to compare with what is read back
+#[derive(Debug)]
+struct Written {
+    path: PathBuf,
+    hasher: DefaultHasher,
+    len: u64,
+}
+
+impl<W> VerifyAfterClose<W> {
+    /// Wraps a writer for the file at the given path
+    pub fn new<P: Into<PathBuf>>(inner: W, path: P) -> Self {
+        VerifyAfterClose {
+            inner,
+            written: Written {
+                path: path.into(),
+                hasher: DefaultHasher::new(),
+                len: 0,
+            },
+        }
+    }
+
+    /// The path that is read back
+    pub fn path(&self) -> &Path {
+        &self.written.path
+    }
+
+    /// Returns the writer without closing it or reading the file back
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl Written {
+    #[track_caller]
+    fn verify(self) -> Result<(), CloseError> {
+        let io_error = match read_back(&self.path) {
+            Ok((len, checksum)) if len == self.len && checksum == self.hasher.finish() => {
+                return Ok(())
+            }
+            Ok((len, _)) if len == self.len => io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("the {} bytes read back differ from the written ones", len),
+            ),
+            Ok((len, _)) => io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "{} bytes were written, but {} were read back",
+                    self.len, len
+                ),
+            ),
+            Err(e) => e,
+        };
+        Err(CloseError::detached(io_error, CloseStage::Verify).with_path(self.path))
+    }
+}
+
+fn read_back(path: &Path) -> io::Result<(u64, u64)> {
+    let mut file = fs::File::open(path)?;
+    let mut hasher = DefaultHasher::new();
+    let mut len = 0;
+    let mut buf = vec![0; 64 << 10];
+    loop {
+        match file.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => {
+                hasher.write(&buf[..n]);
+                len += n as u64;
+            }
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    file.close()?;
+    Ok((len, hasher.finish()))
+}
+
+impl<W: Write> Write for VerifyAfterClose<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.written.hasher.write(&buf[..written]);
+        self.written.len += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Closes the writer, then reads the file back
+impl<W: Closable> Closable for VerifyAfterClose<W> {
+    #[track_caller]
+    fn close(self) -> Result<(), CloseError> {
+        self.inner.close()?;
+        self.written.verify()
+    }
+}
+
+/// Applies the options and closes the writer, then reads the file back
+impl<W: SyncClosable> SyncClosable for VerifyAfterClose<W> {
+    #[track_caller]
+    fn close_with_options(self, options: &CloseOptions) -> Result<(), CloseError> {
+        self.inner.close_with_options(options)?;
+        self.written.verify()
+    }
+}
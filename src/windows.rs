@@ -0,0 +1,464 @@
+use crate::{CloseError, CloseErrorKind, Durability, FileSystem, RemoteFailure};
+use std::os::windows::prelude::*;
+use std::{fs, io};
+use winapi::shared::winerror;
+use winapi::um::{fileapi, handleapi, minwinbase, processenv, winbase, winnt, winsock2};
+
+pub(crate) fn kind(code: i32) -> CloseErrorKind {
+    match code as u32 {
+        winerror::ERROR_OPERATION_ABORTED => CloseErrorKind::Interrupted,
+        winerror::ERROR_DISK_FULL | winerror::ERROR_HANDLE_DISK_FULL => CloseErrorKind::OutOfSpace,
+        winerror::ERROR_DISK_QUOTA_EXCEEDED => CloseErrorKind::QuotaExceeded,
+        winerror::ERROR_IO_DEVICE
+        | winerror::ERROR_WRITE_FAULT
+        | winerror::ERROR_CRC
+        | winerror::ERROR_LOST_WRITEBEHIND_DATA => CloseErrorKind::IoFailure,
+        winerror::ERROR_INVALID_HANDLE | winerror::WSAENOTSOCK => CloseErrorKind::InvalidDescriptor,
+        winerror::WSAECONNRESET => CloseErrorKind::ConnectionReset,
+        winerror::WSAENOTCONN => CloseErrorKind::NotConnected,
+        _ => CloseErrorKind::Other,
+    }
+}
+
+pub(crate) fn remote_failure(code: i32) -> Option<RemoteFailure> {
+    match code as u32 {
+        winerror::ERROR_FILE_INVALID => Some(RemoteFailure::StaleHandle),
+        winerror::ERROR_NETNAME_DELETED
+        | winerror::ERROR_UNEXP_NET_ERR
+        | winerror::ERROR_BAD_NETPATH
+        | winerror::ERROR_NETWORK_UNREACHABLE
+        | winerror::ERROR_CONNECTION_ABORTED
+        | winerror::ERROR_SEM_TIMEOUT => Some(RemoteFailure::ConnectionLost),
+        winerror::ERROR_LOST_WRITEBEHIND_DATA
+        | winerror::ERROR_LOST_WRITEBEHIND_DATA_NETWORK_DISCONNECTED
+        | winerror::ERROR_LOST_WRITEBEHIND_DATA_NETWORK_SERVER_ERROR
+        | winerror::ERROR_LOST_WRITEBEHIND_DATA_LOCAL_DISK_ERROR => {
+            Some(RemoteFailure::DelayedWriteFailed)
+        }
+        _ => None,
+    }
+}
+
+/// Whether the handle is still open after CloseHandle failed with the given error code
+pub(crate) fn retained_after_close(_code: i32) -> bool {
+    false
+}
+
+/// Whether syncing can work for the file, i.e. it is not a pipe, socket or character device
+///
+/// If the file type can't be determined, syncing is attempted to not hide genuine failures.
+pub(crate) fn is_syncable(file: &fs::File) -> bool {
+    let file_type = unsafe { fileapi::GetFileType(file.as_raw_handle() as winnt::HANDLE) };
+    !matches!(
+        file_type & !winbase::FILE_TYPE_REMOTE,
+        winbase::FILE_TYPE_CHAR | winbase::FILE_TYPE_PIPE
+    )
+}
+
+pub(crate) fn open_dir(path: &std::path::Path) -> io::Result<fs::File> {
+    // Directories can only be opened with backup semantics
+    fs::OpenOptions::new()
+        .read(true)
+        .custom_flags(winbase::FILE_FLAG_BACKUP_SEMANTICS)
+        .open(path)
+}
+
+/// Detects the file system of the volume the path is on with GetVolumeInformationW
+pub(crate) fn filesystem(path: &std::path::Path) -> io::Result<FileSystem> {
+    let path = wide(path);
+    let mut volume = [0u16; 261];
+    let rc = unsafe {
+        fileapi::GetVolumePathNameW(path.as_ptr(), volume.as_mut_ptr(), volume.len() as u32)
+    };
+    if rc == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let mut name = [0u16; 261];
+    let rc = unsafe {
+        fileapi::GetVolumeInformationW(
+            volume.as_ptr(),
+            std::ptr::null_mut(),
+            0,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+            name.as_mut_ptr(),
+            name.len() as u32,
+        )
+    };
+    if rc == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(filesystem_from_name(&name))
+}
+
+/// Detects the file system of the volume the file is on with GetVolumeInformationByHandleW
+pub(crate) fn filesystem_of_file(file: &fs::File) -> io::Result<FileSystem> {
+    let mut name = [0u16; 261];
+    let rc = unsafe {
+        fileapi::GetVolumeInformationByHandleW(
+            file.as_raw_handle() as winnt::HANDLE,
+            std::ptr::null_mut(),
+            0,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+            name.as_mut_ptr(),
+            name.len() as u32,
+        )
+    };
+    if rc == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(filesystem_from_name(&name))
+}
+
+fn filesystem_from_name(name: &[u16]) -> FileSystem {
+    let len = name.iter().position(|&c| c == 0).unwrap_or(name.len());
+    FileSystem::from_name(&String::from_utf16_lossy(&name[..len]))
+}
+
+pub(crate) fn create_anonymous(_dir: &std::path::Path) -> io::Result<Option<fs::File>> {
+    Ok(None)
+}
+
+pub(crate) fn link_anonymous(_file: &fs::File, _path: &std::path::Path) -> io::Result<()> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+#[link(name = "kernel32")]
+extern "system" {
+    // winapi declares ReplaceFileW without its BOOL return value
+    fn ReplaceFileW(
+        replaced: winnt::LPCWSTR,
+        replacement: winnt::LPCWSTR,
+        backup: winnt::LPCWSTR,
+        flags: u32,
+        exclude: *mut std::ffi::c_void,
+        reserved: *mut std::ffi::c_void,
+    ) -> i32;
+}
+
+fn wide(path: &std::path::Path) -> Vec<u16> {
+    path.as_os_str().encode_wide().chain(Some(0)).collect()
+}
+
+/// Replaces the target with the replacement file with ReplaceFileW
+///
+/// Unlike a rename this keeps the ACLs, attributes and alternate data streams of the target. If
+/// a backup path is given, the target is moved there. If the target doesn't exist, the
+/// replacement is renamed.
+pub(crate) fn replace_file(
+    replacement: &std::path::Path,
+    target: &std::path::Path,
+    backup: Option<&std::path::Path>,
+) -> io::Result<()> {
+    let backup = backup.map(wide);
+    let rc = unsafe {
+        ReplaceFileW(
+            wide(target).as_ptr(),
+            wide(replacement).as_ptr(),
+            backup
+                .as_ref()
+                .map_or(std::ptr::null(), |backup| backup.as_ptr()),
+            winbase::REPLACEFILE_IGNORE_MERGE_ERRORS | winbase::REPLACEFILE_IGNORE_ACL_ERRORS,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+        )
+    };
+    if rc != 0 {
+        return Ok(());
+    }
+    let err = io::Error::last_os_error();
+    if err.raw_os_error() == Some(winerror::ERROR_FILE_NOT_FOUND as i32) {
+        return rename_write_through(replacement, target);
+    }
+    Err(err)
+}
+
+/// Renames the file with MoveFileExW, returning once the rename is flushed to disk
+pub(crate) fn rename_write_through(from: &std::path::Path, to: &std::path::Path) -> io::Result<()> {
+    let rc = unsafe {
+        winbase::MoveFileExW(
+            wide(from).as_ptr(),
+            wide(to).as_ptr(),
+            winbase::MOVEFILE_REPLACE_EXISTING | winbase::MOVEFILE_WRITE_THROUGH,
+        )
+    };
+    if rc == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+pub(crate) fn set_write_through(options: &mut fs::OpenOptions, durability: Durability) {
+    if durability != Durability::None {
+        options.custom_flags(winbase::FILE_FLAG_WRITE_THROUGH);
+    }
+}
+
+pub(crate) fn lock(file: &fs::File, exclusive: bool) -> io::Result<()> {
+    let flags = if exclusive {
+        minwinbase::LOCKFILE_EXCLUSIVE_LOCK
+    } else {
+        0
+    };
+    let mut overlapped: minwinbase::OVERLAPPED = unsafe { std::mem::zeroed() };
+    // Locks the whole file, as flock(2) does
+    let rc = unsafe {
+        fileapi::LockFileEx(
+            file.as_raw_handle() as winnt::HANDLE,
+            flags,
+            0,
+            u32::MAX,
+            u32::MAX,
+            &mut overlapped,
+        )
+    };
+    if rc == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+pub(crate) fn unlock(file: &fs::File) -> io::Result<()> {
+    let mut overlapped: minwinbase::OVERLAPPED = unsafe { std::mem::zeroed() };
+    let rc = unsafe {
+        fileapi::UnlockFileEx(
+            file.as_raw_handle() as winnt::HANDLE,
+            0,
+            u32::MAX,
+            u32::MAX,
+            &mut overlapped,
+        )
+    };
+    if rc == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+pub(crate) fn sync(file: &fs::File, durability: Durability) -> io::Result<()> {
+    match durability {
+        Durability::None => Ok(()),
+        // FlushFileBuffers syncs data and metadata and flushes the drive cache, there are no
+        // weaker levels
+        _ => flush_file_buffers(file),
+    }
+}
+
+/// Calls FlushFileBuffers, skipping handles that can't be flushed
+///
+/// FlushFileBuffers fails with ERROR_ACCESS_DENIED for handles opened without GENERIC_WRITE, which
+/// have no data to flush, and for volume handles of non-administrators. Both are treated as
+/// nothing to sync, like fsync(2) on a read-only file descriptor on unix.
+fn flush_file_buffers(file: &fs::File) -> io::Result<()> {
+    if unsafe { fileapi::FlushFileBuffers(file.as_raw_handle() as winnt::HANDLE) } != 0 {
+        return Ok(());
+    }
+    let err = io::Error::last_os_error();
+    if err.raw_os_error() == Some(winerror::ERROR_ACCESS_DENIED as i32) {
+        return Ok(());
+    }
+    Err(err)
+}
+
+pub(crate) fn sync_filesystems(files: &[fs::File]) -> Vec<bool> {
+    vec![false; files.len()]
+}
+
+pub(crate) fn start_writeback(_file: &fs::File, _offset: u64, _len: u64) -> io::Result<()> {
+    Ok(())
+}
+
+pub(crate) fn wait_writeback(_file: &fs::File, _offset: u64, _len: u64) -> io::Result<()> {
+    Ok(())
+}
+
+pub(crate) fn drop_cache(_file: &fs::File) -> io::Result<()> {
+    Ok(())
+}
+
+#[track_caller]
+pub(crate) fn close(handle: OwnedHandle) -> Result<(), CloseError> {
+    let handle = handle.into_raw_handle();
+    let rc = unsafe { kernel32::CloseHandle(handle) };
+    if rc != 0 {
+        Ok(())
+    } else {
+        let io_error = io::Error::last_os_error();
+        let owned = io_error
+            .raw_os_error()
+            .filter(|&code| retained_after_close(code))
+            .map(|_| unsafe { OwnedHandle::from_raw_handle(handle) });
+        Err(CloseError::new(io_error, handle, owned))
+    }
+}
+
+/// Closes the handle of the standard stream, which is replaced by no handle
+///
+/// Writes to a missing standard stream are ignored by the standard library.
+#[track_caller]
+fn close_stdio(id: u32) -> Result<(), CloseError> {
+    let handle = unsafe { processenv::GetStdHandle(id) };
+    if handle.is_null() || handle == handleapi::INVALID_HANDLE_VALUE {
+        return Ok(());
+    }
+    if unsafe { processenv::SetStdHandle(id, std::ptr::null_mut()) } == 0 {
+        return Err(CloseError::detached(
+            io::Error::last_os_error(),
+            crate::CloseStage::Close,
+        ));
+    }
+    close(unsafe { OwnedHandle::from_raw_handle(handle as RawHandle) })
+}
+
+#[track_caller]
+pub(crate) fn close_stdout() -> Result<(), CloseError> {
+    close_stdio(winbase::STD_OUTPUT_HANDLE)
+}
+
+#[track_caller]
+pub(crate) fn close_stderr() -> Result<(), CloseError> {
+    close_stdio(winbase::STD_ERROR_HANDLE)
+}
+
+impl crate::Closable for fs::File {
+    #[track_caller]
+    fn close(self) -> Result<(), CloseError> {
+        close(self.into())
+    }
+}
+
+/// Closes any kind of kernel handle with CloseHandle, e.g. of a process, pipe or event
+///
+/// Sockets must not be closed with CloseHandle, use the Closable impl of OwnedSocket instead.
+impl crate::Closable for OwnedHandle {
+    #[track_caller]
+    fn close(self) -> Result<(), CloseError> {
+        close(self)
+    }
+}
+
+/// Syncs like a file, pipes and character devices are only closed
+impl crate::SyncClosable for OwnedHandle {
+    #[track_caller]
+    fn close_with_options(self, options: &crate::CloseOptions) -> Result<(), CloseError> {
+        fs::File::from(self).close_with_options(options)
+    }
+}
+
+#[track_caller]
+pub(crate) fn close_socket(socket: OwnedSocket) -> Result<(), CloseError> {
+    let socket = socket.into_raw_socket() as winsock2::SOCKET;
+    if unsafe { winsock2::closesocket(socket) } == 0 {
+        return Ok(());
+    }
+    let mut code = unsafe { winsock2::WSAGetLastError() };
+    if code == winerror::WSAEWOULDBLOCK as i32 {
+        // A non-blocking socket with a linger timeout stays open, closing it in blocking mode
+        // waits for the timeout instead
+        let mut nonblocking = 0;
+        unsafe { winsock2::ioctlsocket(socket, winsock2::FIONBIO, &mut nonblocking) };
+        if unsafe { winsock2::closesocket(socket) } == 0 {
+            return Ok(());
+        }
+        code = unsafe { winsock2::WSAGetLastError() };
+    }
+    let io_error = io::Error::from_raw_os_error(code);
+    Err(CloseError::new(io_error, socket as RawHandle, None))
+}
+
+/// Closes a socket with closesocket, reporting the error of WSAGetLastError
+impl crate::Closable for OwnedSocket {
+    #[track_caller]
+    fn close(self) -> Result<(), CloseError> {
+        close_socket(self)
+    }
+}
+
+pub(crate) fn set_linger(
+    stream: &std::net::TcpStream,
+    linger: std::time::Duration,
+) -> io::Result<()> {
+    use std::convert::TryInto;
+    let linger = winsock2::linger {
+        l_onoff: 1,
+        l_linger: crate::net::linger_secs(linger)
+            .try_into()
+            .unwrap_or(u16::MAX),
+    };
+    let rc = unsafe {
+        winsock2::setsockopt(
+            stream.as_raw_socket() as winsock2::SOCKET,
+            winsock2::SOL_SOCKET,
+            winsock2::SO_LINGER,
+            &linger as *const winsock2::linger as *const i8,
+            std::mem::size_of::<winsock2::linger>() as i32,
+        )
+    };
+    if rc != 0 {
+        return Err(io::Error::from_raw_os_error(unsafe {
+            winsock2::WSAGetLastError()
+        }));
+    }
+    Ok(())
+}
+
+impl crate::Closable for std::net::TcpStream {
+    #[track_caller]
+    fn close(self) -> Result<(), CloseError> {
+        close_socket(self.into())
+    }
+}
+
+impl crate::Closable for std::net::TcpListener {
+    #[track_caller]
+    fn close(self) -> Result<(), CloseError> {
+        close_socket(self.into())
+    }
+}
+
+impl crate::Closable for std::net::UdpSocket {
+    #[track_caller]
+    fn close(self) -> Result<(), CloseError> {
+        close_socket(self.into())
+    }
+}
+
+/// Closes the pipe to the child, which signals the end of its input
+impl crate::Closable for std::process::ChildStdin {
+    #[track_caller]
+    fn close(self) -> Result<(), CloseError> {
+        close(self.into())
+    }
+}
+
+impl crate::Closable for std::process::ChildStdout {
+    #[track_caller]
+    fn close(self) -> Result<(), CloseError> {
+        close(self.into())
+    }
+}
+
+impl crate::Closable for std::process::ChildStderr {
+    #[track_caller]
+    fn close(self) -> Result<(), CloseError> {
+        close(self.into())
+    }
+}
+
+impl crate::Closable for std::io::PipeReader {
+    #[track_caller]
+    fn close(self) -> Result<(), CloseError> {
+        close(self.into())
+    }
+}
+
+/// Closes the write end of the pipe, which signals the end of the data to the reader
+impl crate::Closable for std::io::PipeWriter {
+    #[track_caller]
+    fn close(self) -> Result<(), CloseError> {
+        close(self.into())
+    }
+}
@@ -0,0 +1,123 @@
+use crate::{Closable, CloseError, CloseOptions, Durability, TokenClosable};
+use std::io::{self, Read, Seek, Write};
+use std::ops::{Deref, DerefMut};
+
+/// A resource that is still open, closing it yields a Closed witness
+///
+/// Functions that publish data can take Closed as an argument, so forgetting to close before
+/// publishing becomes a compile error instead of a silently truncated file.
+///
+/// ```
+/// use close_file::{Closed, Open};
+/// use std::io::Write;
+///
+/// fn publish(_proof: Closed) {}
+///
+/// let mut f = Open::new(std::fs::File::create("temp").unwrap());
+/// f.write_all(b"Hello, world!").unwrap();
+/// publish(f.close().unwrap());
+/// ```
+#[derive(Debug)]
+pub struct Open<F: Closable> {
+    inner: F,
+}
+
+impl<F: Closable> Open<F> {
+    /// Wraps an open resource
+    pub fn new(inner: F) -> Self {
+        Open { inner }
+    }
+
+    /// Returns the resource without closing it, no Closed witness is produced
+    pub fn into_inner(self) -> F {
+        self.inner
+    }
+
+    /// Closes the resource and returns the witness
+    #[track_caller]
+    pub fn close(self) -> Result<Closed, CloseError> {
+        self.inner.close()?;
+        Ok(Closed {
+            durability: Durability::None,
+        })
+    }
+
+    /// Syncs the resource with the given durability level, then closes it and returns the witness
+    ///
+    /// The witness records the durability that was achieved, e.g. Durability::None for pipes,
+    /// which can't be synced, see CloseToken::durability().
+    #[track_caller]
+    pub fn close_with(self, durability: Durability) -> Result<Closed, CloseError>
+    where
+        F: TokenClosable,
+    {
+        let token = self
+            .inner
+            .close_with_token(&CloseOptions::new().durability(durability))?;
+        Ok(Closed {
+            durability: token.durability(),
+        })
+    }
+
+    /// Syncs file content and metadata, then closes it and returns the witness
+    ///
+    /// This is the same as close_with(Durability::FullSync).
+    #[track_caller]
+    pub fn close_synced(self) -> Result<Closed, CloseError>
+    where
+        F: TokenClosable,
+    {
+        self.close_with(Durability::FullSync)
+    }
+}
+
+impl<F: Closable> Deref for Open<F> {
+    type Target = F;
+
+    fn deref(&self) -> &F {
+        &self.inner
+    }
+}
+
+impl<F: Closable> DerefMut for Open<F> {
+    fn deref_mut(&mut self) -> &mut F {
+        &mut self.inner
+    }
+}
+
+impl<F: Closable + Read> Read for Open<F> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.inner.read(buf)
+    }
+}
+
+impl<F: Closable + Write> Write for Open<F> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.inner.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<F: Closable + Seek> Seek for Open<F> {
+    fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+        self.inner.seek(pos)
+    }
+}
+
+/// Proof that a resource was closed without errors
+///
+/// Can only be obtained by closing an Open resource.
+#[derive(Debug)]
+pub struct Closed {
+    durability: Durability,
+}
+
+impl Closed {
+    /// How durable the data was made before closing
+    pub fn durability(&self) -> Durability {
+        self.durability
+    }
+}
@@ -0,0 +1,225 @@
+use crate::{sync_parent_dir, Closable, CloseError, CloseStage, Durability, SyncClosable};
+use std::io::{self, Write};
+use std::path::Path;
+use std::{error, fmt, fs};
+
+/// The error of writing a whole file, by the step that failed
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum WriteError {
+    /// Creating or opening the file failed
+    Open(io::Error),
+    /// Writing or copying the contents failed
+    ///
+    /// The file is closed and errors closing it are discarded, as the content is incomplete anyway.
+    Write(io::Error),
+    /// Syncing or closing the file failed
+    Close(CloseError),
+}
+
+impl WriteError {
+    /// Returns the underlying I/O error
+    pub fn as_io_error(&self) -> &io::Error {
+        match self {
+            WriteError::Open(e) | WriteError::Write(e) => e,
+            WriteError::Close(e) => e.as_io_error(),
+        }
+    }
+}
+
+impl error::Error for WriteError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            WriteError::Open(e) | WriteError::Write(e) => Some(e),
+            WriteError::Close(e) => Some(e),
+        }
+    }
+}
+
+impl fmt::Display for WriteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WriteError::Open(e) => write!(f, "failed to open file: {}", e),
+            WriteError::Write(e) => write!(f, "failed to write file: {}", e),
+            WriteError::Close(e) => fmt::Display::fmt(e, f),
+        }
+    }
+}
+
+/// Converts the error into an io::Error of the same kind
+///
+/// Open and write errors are returned as they are, close errors are wrapped like
+/// `io::Error::from(CloseError)` does.
+impl From<WriteError> for io::Error {
+    fn from(err: WriteError) -> io::Error {
+        match err {
+            WriteError::Open(e) | WriteError::Write(e) => e,
+            WriteError::Close(e) => e.into(),
+        }
+    }
+}
+
+impl From<CloseError> for WriteError {
+    fn from(err: CloseError) -> WriteError {
+        WriteError::Close(err)
+    }
+}
+
+/// Writes the contents to the file at the given path and closes it, reporting close errors
+///
+/// Unlike std::fs::write(), errors closing the file are returned. The file is created if it doesn't
+/// exist and truncated otherwise.
+#[track_caller]
+pub fn write_and_close<P: AsRef<Path>, C: AsRef<[u8]>>(
+    path: P,
+    contents: C,
+) -> Result<(), WriteError> {
+    write_and_close_with(path, contents, Durability::None)
+}
+
+/// Writes the contents to the file at the given path, syncs it with the given level and closes it
+#[track_caller]
+pub fn write_and_close_with<P: AsRef<Path>, C: AsRef<[u8]>>(
+    path: P,
+    contents: C,
+    durability: Durability,
+) -> Result<(), WriteError> {
+    let path = path.as_ref();
+    let file = fs::File::create(path).map_err(WriteError::Open)?;
+    finish(file, path, contents.as_ref(), durability)
+}
+
+/// Appends the contents to the file at the given path and closes it, reporting close errors
+///
+/// The file is created if it doesn't exist.
+#[track_caller]
+pub fn append_and_close<P: AsRef<Path>, C: AsRef<[u8]>>(
+    path: P,
+    contents: C,
+) -> Result<(), WriteError> {
+    append_and_close_with(path, contents, Durability::None)
+}
+
+/// Appends the contents to the file at the given path, syncs it with the given level and closes it
+#[track_caller]
+pub fn append_and_close_with<P: AsRef<Path>, C: AsRef<[u8]>>(
+    path: P,
+    contents: C,
+    durability: Durability,
+) -> Result<(), WriteError> {
+    let path = path.as_ref();
+    let file = fs::OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(path)
+        .map_err(WriteError::Open)?;
+    finish(file, path, contents.as_ref(), durability)
+}
+
+#[track_caller]
+fn finish(
+    mut file: fs::File,
+    path: &Path,
+    contents: &[u8],
+    durability: Durability,
+) -> Result<(), WriteError> {
+    if let Err(e) = file.write_all(contents) {
+        return Err(WriteError::Write(e));
+    }
+    file.close_with(durability)
+        .map_err(|e| WriteError::Close(e.with_path(path)))
+}
+
+/// Copies a file durably, returning the number of bytes copied
+///
+/// Unlike std::fs::copy(), the destination is synced like close_synced() and closed, and its
+/// directory is synced, before returning, and errors closing either file are returned. The content
+/// is copied in the kernel where possible, with copy_file_range(2) on Linux and CopyFileExW on
+/// windows. The permissions of the source are copied.
+#[track_caller]
+pub fn copy_durable<P: AsRef<Path>, Q: AsRef<Path>>(from: P, to: Q) -> Result<u64, WriteError> {
+    let (from, to) = (from.as_ref(), to.as_ref());
+    #[cfg(windows)]
+    let (source, dest, copied) = {
+        // CopyFileExW works on paths, the copy is reopened to sync it
+        let copied = fs::copy(from, to).map_err(WriteError::Write)?;
+        let dest = fs::OpenOptions::new()
+            .write(true)
+            .open(to)
+            .map_err(WriteError::Open)?;
+        (None::<fs::File>, dest, copied)
+    };
+    #[cfg(unix)]
+    let (source, dest, copied) = {
+        let mut source = fs::File::open(from).map_err(WriteError::Open)?;
+        let mut dest = fs::File::create(to).map_err(WriteError::Open)?;
+        // io::copy uses copy_file_range(2) for files on Linux
+        let copied = source
+            .metadata()
+            .and_then(|metadata| dest.set_permissions(metadata.permissions()))
+            .and_then(|_| io::copy(&mut source, &mut dest))
+            .map_err(WriteError::Write)?;
+        (Some(source), dest, copied)
+    };
+    dest.close_synced()
+        .map_err(|e| WriteError::Close(e.with_path(to)))?;
+    if let Some(source) = source {
+        source.close_with_path(from).map_err(WriteError::Close)?;
+    }
+    if let Err(e) = sync_parent_dir(to) {
+        let err = CloseError::detached(e, CloseStage::SyncDir).with_path(to);
+        return Err(WriteError::Close(err));
+    }
+    Ok(copied)
+}
+
+/// Creates the file, passes it to the closure, then syncs and closes it
+///
+/// The file can't be closed without checking errors, as it is never handed out. If the closure
+/// fails, its error is returned as WriteError::Write and the file is closed without checking
+/// errors. Otherwise the file is synced like close_synced() and closed, and the value returned by
+/// the closure is returned.
+///
+/// ```
+/// use std::io::Write;
+///
+/// close_file::with_create("temp", |f| f.write_all(b"Hello, world!")).unwrap();
+/// ```
+#[track_caller]
+pub fn with_create<P, T, F>(path: P, f: F) -> Result<T, WriteError>
+where
+    P: AsRef<Path>,
+    F: FnOnce(&mut fs::File) -> io::Result<T>,
+{
+    let path = path.as_ref();
+    let file = fs::File::create(path).map_err(WriteError::Open)?;
+    with(file, path, f)
+}
+
+/// Opens the existing file for reading and writing, passes it to the closure, then syncs and
+/// closes it like with_create()
+#[track_caller]
+pub fn with_file<P, T, F>(path: P, f: F) -> Result<T, WriteError>
+where
+    P: AsRef<Path>,
+    F: FnOnce(&mut fs::File) -> io::Result<T>,
+{
+    let path = path.as_ref();
+    let file = fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(path)
+        .map_err(WriteError::Open)?;
+    with(file, path, f)
+}
+
+#[track_caller]
+fn with<T, F>(mut file: fs::File, path: &Path, f: F) -> Result<T, WriteError>
+where
+    F: FnOnce(&mut fs::File) -> io::Result<T>,
+{
+    let value = f(&mut file).map_err(WriteError::Write)?;
+    file.close_synced()
+        .map_err(|e| WriteError::Close(e.with_path(path)))?;
+    Ok(value)
+}
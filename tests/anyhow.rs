@@ -0,0 +1,17 @@
+#![cfg(feature = "anyhow")]
+
+use close_file::Closable;
+
+mod common;
+
+#[cfg(unix)]
+#[test]
+fn anyhow_close_context() {
+    use close_file::anyhow::CloseResultExt;
+
+    let f = common::closed_file();
+    let err = f.close().close_context("out.txt").unwrap_err();
+    assert_eq!(err.to_string(), "failed to close out.txt");
+    let inner = err.downcast_ref::<close_file::CloseError>().unwrap();
+    assert_eq!(inner.path(), Some(std::path::Path::new("out.txt")));
+}
@@ -0,0 +1,28 @@
+use close_file::{AsyncClosable, Closable, CloseError};
+use std::future::Future;
+use std::task::{Context, Poll, Waker};
+
+struct Pipe(std::io::PipeReader);
+
+impl AsyncClosable for Pipe {
+    async fn close(self) -> Result<(), CloseError> {
+        self.0.close()
+    }
+}
+
+fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = std::pin::pin!(future);
+    let mut cx = Context::from_waker(Waker::noop());
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+    }
+}
+
+#[test]
+fn close_option() {
+    let (reader, _) = std::io::pipe().unwrap();
+    block_on(AsyncClosable::close(Some(Pipe(reader)))).unwrap();
+    block_on(AsyncClosable::close(None::<Pipe>)).unwrap();
+}
@@ -0,0 +1,51 @@
+#![cfg(feature = "async-fs")]
+
+use close_file::async_fs::FileExt;
+use close_file::{AsyncClosable, CloseOptions, Durability};
+use futures_lite::future::block_on;
+use futures_lite::AsyncWriteExt;
+
+#[test]
+fn close() {
+    let path = std::env::temp_dir().join("close-file-async-fs-close");
+
+    block_on(async {
+        let mut f = async_fs::File::create(&path).await.unwrap();
+        f.write_all(b"Hello, world!").await.unwrap();
+        f.close().await.unwrap();
+    });
+    assert_eq!(std::fs::read(&path).unwrap(), b"Hello, world!");
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn close_with_options() {
+    let path = std::env::temp_dir().join("close-file-async-fs-close-with-options");
+
+    block_on(async {
+        let mut f = async_fs::File::create(&path).await.unwrap();
+        f.write_all(b"Hello, world!").await.unwrap();
+        let options = CloseOptions::new().durability(Durability::Sync);
+        f.close_with_options(&options).await.unwrap();
+    });
+    assert_eq!(std::fs::read(&path).unwrap(), b"Hello, world!");
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn close_error_location() {
+    use close_file::CloseStage;
+
+    // A read-only file can't be written, so the flush fails
+    let path = std::env::temp_dir().join("close-file-async-fs-close-error");
+    std::fs::write(&path, b"").unwrap();
+
+    let err = block_on(async {
+        let mut f = async_fs::File::open(&path).await.unwrap();
+        let _ = f.write_all(b"Hello, world!").await;
+        f.close().await.unwrap_err()
+    });
+    assert_eq!(err.stage(), CloseStage::Flush);
+    assert_eq!(err.location().file(), file!());
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,42 @@
+#![cfg(feature = "async-std")]
+
+use async_std::io::WriteExt;
+use close_file::async_std::FileExt;
+use close_file::{AsyncClosable, CloseOptions, Durability};
+
+mod common;
+
+#[async_std::test]
+async fn close() {
+    let path = std::env::temp_dir().join("close-file-async-std-close");
+
+    let mut f = async_std::fs::File::create(&path).await.unwrap();
+    f.write_all(b"Hello, world!").await.unwrap();
+    f.close().await.unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), b"Hello, world!");
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[async_std::test]
+async fn close_with_options() {
+    let path = std::env::temp_dir().join("close-file-async-std-close-with-options");
+
+    let mut f = async_std::fs::File::create(&path).await.unwrap();
+    f.write_all(b"Hello, world!").await.unwrap();
+    let options = CloseOptions::new().durability(Durability::Sync);
+    async_std::task::spawn(f.close_with_options(&options))
+        .await
+        .unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), b"Hello, world!");
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[cfg(unix)]
+#[async_std::test]
+async fn close_error() {
+    let f = async_std::fs::File::from(common::closed_file());
+    let err = f.close().await.unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::EBADF));
+    assert_eq!(err.location().file(), file!());
+    err.leak();
+}
@@ -0,0 +1,163 @@
+use close_file::{AtomicWriteFile, Closable};
+use std::io::Write;
+
+#[test]
+fn replace_file() {
+    let path = std::env::temp_dir().join("close-file-atomic-replace");
+    std::fs::write(&path, "old").unwrap();
+
+    let mut f = AtomicWriteFile::create(&path).unwrap();
+    let temp_path = f.temp_path().unwrap().to_path_buf();
+    f.write_all("Hello, world!".as_bytes()).unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), b"old");
+    f.close().unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), b"Hello, world!");
+    assert!(!temp_path.exists());
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn drop_removes_temp_file() {
+    let path = std::env::temp_dir().join("close-file-atomic-drop");
+
+    let mut f = AtomicWriteFile::create(&path).unwrap();
+    let temp_path = f.temp_path().unwrap().to_path_buf();
+    f.write_all("Hello, world!".as_bytes()).unwrap();
+    assert!(temp_path.exists());
+    drop(f);
+    assert!(!temp_path.exists());
+    assert!(!path.exists());
+}
+
+#[test]
+fn failed_rename() {
+    use close_file::CloseStage;
+
+    let path = std::env::temp_dir().join("close-file-atomic-rename-dir");
+    std::fs::create_dir_all(path.join("child")).unwrap();
+
+    let f = AtomicWriteFile::create(&path).unwrap();
+    let temp_path = f.temp_path().unwrap().to_path_buf();
+    let err = f.close().unwrap_err();
+    assert_eq!(err.stage(), CloseStage::Rename);
+    assert_eq!(err.path(), Some(path.as_path()));
+    assert!(!temp_path.exists());
+    std::fs::remove_dir_all(&path).unwrap();
+}
+
+#[test]
+fn write_atomic() {
+    let path = std::env::temp_dir().join("close-file-write-atomic");
+
+    close_file::write_atomic(&path, "Hello, world!").unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), b"Hello, world!");
+    close_file::write_atomic(&path, b"Bye").unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), b"Bye");
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn write_atomic_close_error() {
+    use close_file::{CloseError, CloseStage};
+
+    let path = std::env::temp_dir().join("close-file-write-atomic-dir");
+    std::fs::create_dir_all(path.join("child")).unwrap();
+
+    let err = close_file::write_atomic(&path, "Hello, world!").unwrap_err();
+    let err = err.into_inner().unwrap().downcast::<CloseError>().unwrap();
+    assert_eq!(err.stage(), CloseStage::Rename);
+    std::fs::remove_dir_all(&path).unwrap();
+}
+
+#[test]
+fn anonymous_temp() {
+    let path = std::env::temp_dir().join("close-file-atomic-anonymous");
+    std::fs::write(&path, "old").unwrap();
+
+    let mut f = AtomicWriteFile::options()
+        .anonymous_temp(true)
+        .create(&path)
+        .unwrap();
+    #[cfg(target_os = "linux")]
+    assert_eq!(f.temp_path(), None);
+    f.write_all("Hello, world!".as_bytes()).unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), b"old");
+    f.close().unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), b"Hello, world!");
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn abort() {
+    let path = std::env::temp_dir().join("close-file-atomic-abort");
+    std::fs::write(&path, "old").unwrap();
+
+    let mut f = AtomicWriteFile::create(&path).unwrap();
+    let temp_path = f.temp_path().unwrap().to_path_buf();
+    f.write_all("Hello, world!".as_bytes()).unwrap();
+    f.abort().unwrap();
+    assert!(!temp_path.exists());
+    assert_eq!(std::fs::read(&path).unwrap(), b"old");
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn on_drop_error() {
+    use close_file::CloseStage;
+    use std::sync::{Arc, Mutex};
+
+    let path = std::env::temp_dir().join("close-file-atomic-drop-error");
+
+    let stages = Arc::new(Mutex::new(Vec::new()));
+    let errors = stages.clone();
+    let f = AtomicWriteFile::options()
+        .on_drop_error(move |e| errors.lock().unwrap().push(e.stage()))
+        .create(&path)
+        .unwrap();
+    std::fs::remove_file(f.temp_path().unwrap()).unwrap();
+    drop(f);
+    assert_eq!(*stages.lock().unwrap(), vec![CloseStage::Remove]);
+}
+
+#[test]
+fn backup() {
+    let path = std::env::temp_dir().join("close-file-atomic-backup");
+    let backup_path = std::env::temp_dir().join("close-file-atomic-backup.bak");
+    std::fs::write(&path, "old").unwrap();
+
+    let mut f = AtomicWriteFile::options()
+        .backup(".bak")
+        .create(&path)
+        .unwrap();
+    assert_eq!(f.backup_path(), Some(backup_path.as_path()));
+    f.write_all("Hello, world!".as_bytes()).unwrap();
+    f.close().unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), b"Hello, world!");
+    assert_eq!(std::fs::read(&backup_path).unwrap(), b"old");
+
+    close_file::AtomicWriteOptions::new()
+        .backup(".bak")
+        .create(&path)
+        .unwrap()
+        .commit()
+        .unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), b"");
+    assert_eq!(std::fs::read(&backup_path).unwrap(), b"Hello, world!");
+    std::fs::remove_file(&path).unwrap();
+    std::fs::remove_file(&backup_path).unwrap();
+}
+
+#[test]
+fn backup_without_target() {
+    let path = std::env::temp_dir().join("close-file-atomic-backup-new");
+
+    let f = AtomicWriteFile::options()
+        .backup(".bak")
+        .create(&path)
+        .unwrap();
+    let backup_path = f.backup_path().unwrap().to_path_buf();
+    f.close().unwrap();
+    assert!(path.exists());
+    assert!(!backup_path.exists());
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,128 @@
+use close_file::{Closable, SyncClosable};
+use std::io::{BufWriter, Write};
+
+#[test]
+fn close_buf_writer() {
+    let path = std::env::temp_dir().join("close-file-buf-writer");
+
+    let mut w = BufWriter::new(std::fs::File::create(&path).unwrap());
+    w.write_all("Hello, world!".as_bytes()).unwrap();
+    w.close().unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), b"Hello, world!");
+
+    let mut w = BufWriter::new(std::fs::File::create(&path).unwrap());
+    w.write_all("Bye".as_bytes()).unwrap();
+    w.close_synced().unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), b"Bye");
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn failed_flush_returns_unwritten() {
+    use close_file::CloseStage;
+
+    let mut w = BufWriter::new(
+        std::fs::OpenOptions::new()
+            .write(true)
+            .open("/dev/full")
+            .unwrap(),
+    );
+    w.write_all("Hello, world!".as_bytes()).unwrap();
+    let mut err = w.close().unwrap_err();
+    assert_eq!(err.stage(), CloseStage::Flush);
+    assert_eq!(err.raw_os_error(), Some(libc::ENOSPC));
+    assert_eq!(err.take_unwritten().unwrap(), b"Hello, world!");
+    assert!(err.unwritten().is_none());
+    assert!(err.into_file().is_some());
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn failed_flush_retains_inner_writer() {
+    let mut w = BufWriter::new(close_file::fs::File::create("/dev/full").unwrap());
+    w.write_all("Hello, world!".as_bytes()).unwrap();
+    let mut err = w.close().unwrap_err();
+    assert!(err.take_retained::<std::fs::File>().is_none());
+    let inner = err.take_retained::<close_file::fs::File>().unwrap();
+    assert!(err.take_retained::<close_file::fs::File>().is_none());
+    inner.close().unwrap();
+}
+
+#[test]
+fn close_nested_buf_writers() {
+    let path = std::env::temp_dir().join("close-file-nested-buf-writers");
+
+    let mut w = BufWriter::new(BufWriter::new(close_file::fs::File::create(&path).unwrap()));
+    w.write_all("Hello, world!".as_bytes()).unwrap();
+    w.close_synced().unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), b"Hello, world!");
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn close_line_writer_and_buf_reader() {
+    use std::io::{BufRead, BufReader, LineWriter};
+
+    let path = std::env::temp_dir().join("close-file-line-writer");
+
+    let mut w = LineWriter::new(std::fs::File::create(&path).unwrap());
+    w.write_all("Hello,\nworld!".as_bytes()).unwrap();
+    w.close().unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), b"Hello,\nworld!");
+
+    let mut r = BufReader::new(std::fs::File::open(&path).unwrap());
+    let mut line = String::new();
+    r.read_line(&mut line).unwrap();
+    assert_eq!(line, "Hello,\n");
+    r.close().unwrap();
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn failed_line_writer_flush() {
+    use close_file::CloseStage;
+    use std::io::LineWriter;
+
+    let mut w = LineWriter::new(
+        std::fs::OpenOptions::new()
+            .write(true)
+            .open("/dev/full")
+            .unwrap(),
+    );
+    let _ = w.write_all("Hello, world!".as_bytes());
+    let err = w.close().unwrap_err();
+    assert_eq!(err.stage(), CloseStage::Flush);
+    assert_eq!(err.raw_os_error(), Some(libc::ENOSPC));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn try_close_buf_writer() {
+    use close_file::{TryClosable, TryCloseError};
+
+    let full = std::fs::OpenOptions::new()
+        .write(true)
+        .open("/dev/full")
+        .unwrap();
+    let mut writer = BufWriter::new(full);
+    writer.write_all(b"Hello, world!").unwrap();
+    let writer = match writer.try_close() {
+        Err(TryCloseError::Retained(writer, e)) => {
+            assert_eq!(e.raw_os_error(), Some(libc::ENOSPC));
+            writer
+        }
+        other => panic!("unexpected result: {:?}", other.err()),
+    };
+    assert_eq!(writer.buffer(), b"Hello, world!");
+
+    // Retry on a file that has space
+    let path = std::env::temp_dir().join("close-file-buffered-try-close");
+    let (_, buffer) = writer.into_parts();
+    let mut writer = BufWriter::new(std::fs::File::create(&path).unwrap());
+    writer.write_all(&buffer.unwrap()).unwrap();
+    writer.try_close().unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), b"Hello, world!");
+    std::fs::remove_file(&path).unwrap();
+}
@@ -1,6 +1,8 @@
 use close_file::Closable;
 use std::io::Write;
 
+mod common;
+
 #[test]
 fn close() {
     const FILE_PATH: &str = "temp";
@@ -10,3 +12,67 @@ fn close() {
     f.close().unwrap();
     std::fs::remove_file(FILE_PATH).unwrap();
 }
+
+#[cfg(unix)]
+#[test]
+fn io_error_is_not_source() {
+    use std::error::Error;
+
+    let f = common::closed_file();
+    let err = f.close().unwrap_err();
+    // The I/O error is already in the message, so error chains print it only once
+    assert!(err.source().is_none());
+    assert!(err.to_string().ends_with(&err.as_io_error().to_string()));
+}
+
+#[test]
+fn checked_close() {
+    use close_file::CheckedClose;
+
+    struct Compressor(Vec<u8>);
+
+    impl CheckedClose for Compressor {
+        type Error = String;
+
+        fn checked_close(self) -> Result<(), String> {
+            if self.0.is_empty() {
+                Err("no data was written".to_owned())
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    fn finish<T: CheckedClose>(output: T) -> Result<(), T::Error> {
+        output.checked_close()
+    }
+
+    let path = std::env::temp_dir().join("close-file-checked-close");
+
+    finish(std::fs::File::create(&path).unwrap()).unwrap();
+    finish(Compressor(b"Hello, world!".to_vec())).unwrap();
+    assert_eq!(
+        finish(Compressor(Vec::new())).unwrap_err(),
+        "no data was written"
+    );
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn close_dyn() {
+    use close_file::DynClosable;
+
+    let path = std::env::temp_dir().join("close-file-close-dyn");
+
+    let (reader, writer) = std::io::pipe().unwrap();
+    let resources: Vec<Box<dyn DynClosable + Send>> = vec![
+        Box::new(std::fs::File::create(&path).unwrap()),
+        Box::new(reader),
+        Box::new(writer),
+    ];
+    resources.close().unwrap();
+
+    let f: Box<dyn DynClosable> = Box::new(std::fs::File::open(&path).unwrap());
+    f.close_boxed().unwrap();
+    std::fs::remove_file(&path).unwrap();
+}
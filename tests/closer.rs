@@ -0,0 +1,78 @@
+use close_file::{Closer, Durability};
+use std::io::Write;
+use std::time::Duration;
+
+mod common;
+
+#[test]
+fn close() {
+    let path = std::env::temp_dir().join("close-file-closer");
+
+    let mut f = std::fs::File::create(&path).unwrap();
+    f.write_all("Hello, world!".as_bytes()).unwrap();
+    Closer::new()
+        .sync(Durability::Sync)
+        .start_writeback(true)
+        .drop_cache(true)
+        .retry(3)
+        .timeout(Duration::from_secs(30))
+        .close(f)
+        .unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), b"Hello, world!");
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn on_error() {
+    use close_file::CloseErrorKind;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    let errors = Arc::new(AtomicUsize::new(0));
+    let counter = errors.clone();
+    let closer = Closer::new().on_error(move |_| {
+        counter.fetch_add(1, Ordering::SeqCst);
+    });
+    let f = common::closed_file();
+    let err = closer.close(f).unwrap_err();
+    assert_eq!(err.kind(), CloseErrorKind::InvalidDescriptor);
+    assert_eq!(errors.load(Ordering::SeqCst), 1);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn delete_on_failure() {
+    use close_file::{Cleanup, FailurePolicy};
+
+    let path = std::env::temp_dir().join("close-file-closer-delete-on-failure");
+
+    std::os::unix::fs::symlink("/proc/self/status", &path).unwrap();
+    let f = close_file::fs::File::open(&path).unwrap();
+    let err = Closer::new()
+        .sync(Durability::Sync)
+        .on_failure(FailurePolicy::Delete)
+        .close(f)
+        .unwrap_err();
+    assert!(matches!(err.cleanup(), Some(Cleanup::Removed)));
+    assert!(std::fs::symlink_metadata(&path).is_err());
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn failure_policy_without_path() {
+    use close_file::{Cleanup, FailurePolicy};
+
+    let f = std::fs::File::open("/proc/self/status").unwrap();
+    let err = Closer::new()
+        .sync(Durability::Sync)
+        .on_failure(FailurePolicy::Delete)
+        .close(f)
+        .unwrap_err();
+    match err.cleanup() {
+        Some(Cleanup::Failed(e)) => assert_eq!(e.kind(), std::io::ErrorKind::InvalidInput),
+        cleanup => panic!("unexpected cleanup {:?}", cleanup),
+    }
+    // The file is kept open, as nothing was cleaned up
+    err.retry().unwrap();
+}
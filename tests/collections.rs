@@ -0,0 +1,118 @@
+use close_file::Closable;
+use std::io::Write;
+
+mod common;
+
+#[test]
+fn close_option() {
+    let path = std::env::temp_dir().join("close-file-close-option");
+
+    let f = Some(std::fs::File::create(&path).unwrap());
+    f.close().unwrap();
+    None::<std::fs::File>.close().unwrap();
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn close_vec() {
+    let path = std::env::temp_dir().join("close-file-close-vec");
+
+    let files = vec![
+        std::fs::File::create(&path).unwrap(),
+        std::fs::File::open(&path).unwrap(),
+    ];
+    files.close().unwrap();
+    [
+        std::fs::File::open(&path).unwrap(),
+        std::fs::File::open(&path).unwrap(),
+    ]
+    .close()
+    .unwrap();
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn close_vec_errors() {
+    use close_file::CloseErrorKind;
+    use std::os::unix::io::AsRawFd;
+
+    let path = std::env::temp_dir().join("close-file-close-vec-errors");
+
+    let files = vec![
+        common::closed_file(),
+        std::fs::File::create(&path).unwrap(),
+        common::closed_file(),
+    ];
+    let fd = files[2].as_raw_fd();
+    let err = files.close().unwrap_err();
+    assert_eq!(err.kind(), CloseErrorKind::InvalidDescriptor);
+    let errors = err.into_errors().unwrap();
+    assert_eq!(errors.indices().collect::<Vec<_>>(), vec![0, 2]);
+    assert_eq!(errors.errors()[1].1.raw_fd(), fd);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn close_tuple() {
+    let path = std::env::temp_dir().join("close-file-close-tuple");
+
+    let (reader, writer) = std::io::pipe().unwrap();
+    (std::fs::File::create(&path).unwrap(), reader, writer)
+        .close()
+        .unwrap();
+
+    let f = common::closed_file();
+    let err = (std::fs::File::open(&path).unwrap(), f)
+        .close()
+        .unwrap_err();
+    assert_eq!(err.errors().unwrap().indices().collect::<Vec<_>>(), vec![1]);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn close_all() {
+    let path = std::env::temp_dir().join("close-file-close-all");
+
+    let files = vec![
+        common::closed_file(),
+        std::fs::File::create(&path).unwrap(),
+        common::closed_file(),
+    ];
+    let errors = close_file::close_all(files).unwrap_err();
+    assert_eq!(errors.indices().collect::<Vec<_>>(), vec![0, 2]);
+
+    // The file after the failed one is dropped
+    let files = vec![
+        std::fs::File::open(&path).unwrap(),
+        common::closed_file(),
+        std::fs::File::open(&path).unwrap(),
+    ];
+    let errors = close_file::close_all_fail_fast(files).unwrap_err();
+    assert_eq!(errors.indices().collect::<Vec<_>>(), vec![1]);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn close_file_set() {
+    use close_file::FileSet;
+
+    let path = std::env::temp_dir().join("close-file-close-file-set");
+
+    let mut files = FileSet::new();
+    files.insert("a", std::fs::File::create(&path).unwrap());
+    files.insert("b", common::closed_file());
+    files
+        .get_mut(&"a")
+        .unwrap()
+        .write_all(b"Hello, world!")
+        .unwrap();
+    assert_eq!(files.keys().collect::<Vec<_>>(), vec![&"a", &"b"]);
+    let results = files.close_all();
+    assert!(results[&"a"].is_ok());
+    assert!(results[&"b"].is_err());
+    std::fs::remove_file(&path).unwrap();
+}
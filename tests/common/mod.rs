@@ -0,0 +1,34 @@
+//! Fixtures shared by the integration tests
+
+#[cfg(unix)]
+use std::sync::atomic::{AtomicI32, Ordering};
+
+// Counts down from the descriptor limit, open(2) takes the lowest free number and never gets there
+#[cfg(unix)]
+static CLOSED: AtomicI32 = AtomicI32::new(0);
+
+/// Returns a file whose descriptor was closed behind its back, so closing it fails with EBADF
+///
+/// The descriptor is a real one, dup'd from a pipe to a number near the descriptor limit that no
+/// other test gets, so it can't be reused while the test runs.
+#[cfg(unix)]
+pub fn closed_file() -> std::fs::File {
+    use std::os::unix::io::{AsRawFd, FromRawFd};
+
+    let mut limit = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    assert_eq!(
+        unsafe { libc::getrlimit(libc::RLIMIT_NOFILE, &mut limit) },
+        0
+    );
+    let top = limit.rlim_cur.min(65536) as i32 - 1;
+    let (reader, _writer) = std::io::pipe().unwrap();
+    let min = top - CLOSED.fetch_add(1, Ordering::Relaxed);
+    let fd = unsafe { libc::fcntl(reader.as_raw_fd(), libc::F_DUPFD_CLOEXEC, min) };
+    assert!(fd >= 0, "{}", std::io::Error::last_os_error());
+    let f = unsafe { std::fs::File::from_raw_fd(fd) };
+    assert_eq!(unsafe { libc::close(fd) }, 0);
+    f
+}
@@ -0,0 +1,58 @@
+#![cfg(feature = "derive")]
+
+use close_file::Closable;
+
+mod common;
+
+#[derive(Closable)]
+struct Table {
+    data: std::fs::File,
+    #[close(order = 0)]
+    index: std::fs::File,
+    #[close(skip)]
+    #[allow(dead_code)]
+    name: String,
+}
+
+#[derive(Closable)]
+struct Pipe(std::io::PipeReader, std::io::PipeWriter);
+
+#[derive(Closable)]
+struct Generic<T> {
+    inner: Option<T>,
+}
+
+#[test]
+fn derive() {
+    let path = std::env::temp_dir().join("close-file-derive");
+
+    let table = Table {
+        data: std::fs::File::create(&path).unwrap(),
+        index: std::fs::File::open(&path).unwrap(),
+        name: "table".to_owned(),
+    };
+    table.close().unwrap();
+    let (reader, writer) = std::io::pipe().unwrap();
+    Pipe(reader, writer).close().unwrap();
+    let generic = Generic {
+        inner: Some(std::fs::File::open(&path).unwrap()),
+    };
+    generic.close().unwrap();
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn derive_errors() {
+    let path = std::env::temp_dir().join("close-file-derive-errors");
+
+    let table = Table {
+        data: common::closed_file(),
+        index: std::fs::File::create(&path).unwrap(),
+        name: "table".to_owned(),
+    };
+    let err = table.close().unwrap_err();
+    let errors = err.into_errors().unwrap();
+    assert_eq!(errors.indices().collect::<Vec<_>>(), vec![0]);
+    std::fs::remove_file(&path).unwrap();
+}
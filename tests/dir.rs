@@ -0,0 +1,68 @@
+use close_file::{sync_parent_dir, Closable};
+use std::io::Write;
+
+#[test]
+fn sync_parent_dir_after_create() {
+    let path = std::env::temp_dir().join("close-file-sync-parent-dir");
+
+    let mut f = std::fs::File::create(&path).unwrap();
+    f.write_all("Hello, world!".as_bytes()).unwrap();
+    f.close().unwrap();
+    sync_parent_dir(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn sync_parent_dir_relative() {
+    sync_parent_dir("Cargo.toml").unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn sync_missing_parent_dir() {
+    let err = sync_parent_dir("close-file-missing-dir/file").unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+}
+
+#[test]
+fn dir_sync_and_close() {
+    use close_file::{Dir, SyncClosable};
+
+    let dir = Dir::open(std::env::temp_dir()).unwrap();
+    assert_eq!(dir.path(), std::env::temp_dir());
+    dir.sync().unwrap();
+    dir.close().unwrap();
+    Dir::open(std::env::temp_dir())
+        .unwrap()
+        .close_synced()
+        .unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn dir_open_file() {
+    let err = close_file::Dir::open("Cargo.toml").unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::ENOTDIR));
+}
+
+#[test]
+fn rename_durable() {
+    let dir = std::env::temp_dir().join("close-file-rename-durable");
+    std::fs::create_dir_all(&dir).unwrap();
+    let from = std::env::temp_dir().join("close-file-rename-durable-from");
+    let to = dir.join("to");
+
+    std::fs::write(&from, "Hello, world!").unwrap();
+    close_file::rename_durable(&from, &to).unwrap();
+    assert!(!from.exists());
+    assert_eq!(std::fs::read(&to).unwrap(), b"Hello, world!");
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn rename_durable_missing() {
+    let from = std::env::temp_dir().join("close-file-rename-durable-missing");
+
+    let err = close_file::rename_durable(&from, "close-file-rename-durable-to").unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+}
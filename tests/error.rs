@@ -0,0 +1,133 @@
+use close_file::Closable;
+
+mod common;
+
+#[cfg(unix)]
+#[test]
+fn into_io_error_keeps_fd() {
+    use close_file::CloseError;
+    use std::os::unix::io::AsRawFd;
+
+    let f = common::closed_file();
+    let fd = f.as_raw_fd();
+    let err: std::io::Error = f.close().unwrap_err().into();
+    let inner = err.get_ref().unwrap().downcast_ref::<CloseError>().unwrap();
+    assert_eq!(inner.raw_fd(), fd);
+}
+
+#[cfg(unix)]
+#[test]
+fn kind_of_bad_descriptor() {
+    use close_file::CloseErrorKind;
+
+    let f = common::closed_file();
+    let err = f.close().unwrap_err();
+    assert_eq!(err.kind(), CloseErrorKind::InvalidDescriptor);
+}
+
+#[cfg(unix)]
+#[test]
+fn bad_descriptor_is_not_retryable() {
+    let f = common::closed_file();
+    let err = f.close().unwrap_err();
+    assert!(!err.is_retryable());
+    assert!(!err.is_data_loss());
+    assert!(!err.is_out_of_space());
+    assert!(!err.is_quota_exceeded());
+    assert!(!err.is_io_failure());
+    assert_eq!(err.remote_failure(), None);
+    assert_eq!(err.raw_os_error(), Some(9)); // EBADF
+}
+
+#[cfg(unix)]
+#[test]
+fn retry_returns_unretryable_error() {
+    use close_file::CloseErrorKind;
+    use std::os::unix::io::AsRawFd;
+
+    let f = common::closed_file();
+    let fd = f.as_raw_fd();
+    let err = f.close().unwrap_err().retry().unwrap_err();
+    assert_eq!(err.kind(), CloseErrorKind::InvalidDescriptor);
+    assert_eq!(err.raw_fd(), fd);
+}
+
+#[cfg(unix)]
+#[test]
+fn released_descriptor_is_not_owned() {
+    let f = common::closed_file();
+    let err = f.close().unwrap_err();
+    assert!(err.as_fd().is_none());
+    assert_eq!(err.leak(), None);
+}
+
+#[cfg(unix)]
+#[test]
+fn released_descriptor_gives_no_file() {
+    let f = common::closed_file();
+    assert!(f.close().unwrap_err().into_file().is_none());
+}
+
+#[cfg(unix)]
+#[test]
+fn close_with_path() {
+    use std::os::unix::io::AsRawFd;
+
+    let f = common::closed_file();
+    let fd = f.as_raw_fd();
+    let err = f.close_with_path("/tmp/out.txt").unwrap_err();
+    assert_eq!(err.path(), Some(std::path::Path::new("/tmp/out.txt")));
+    assert!(err
+        .to_string()
+        .starts_with(&format!("failed to close /tmp/out.txt (fd {}): ", fd)));
+    assert!(format!("{:#}", err).starts_with("/tmp/out.txt: "));
+}
+
+#[test]
+fn close_error_is_send_and_sync() {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<close_file::CloseError>();
+}
+
+#[cfg(unix)]
+#[test]
+fn location_of_close() {
+    let f = common::closed_file();
+    let line = line!() + 1;
+    let err = f.close().unwrap_err();
+    assert_eq!(err.location().file(), file!());
+    assert_eq!(err.location().line(), line);
+}
+
+#[cfg(unix)]
+#[test]
+fn info() {
+    use close_file::{CloseErrorKind, CloseStage};
+
+    let f = common::closed_file();
+    let err = f.close_with_path("out.txt").unwrap_err();
+    let info = err.info();
+    assert_eq!(info.kind, CloseErrorKind::InvalidDescriptor);
+    assert_eq!(info.os_error, err.as_io_error().raw_os_error());
+    assert_eq!(info.path.as_deref(), Some(std::path::Path::new("out.txt")));
+    assert_eq!(info.stage, CloseStage::Close);
+    assert_eq!(info.clone(), info);
+}
+
+#[cfg(unix)]
+#[test]
+fn advice() {
+    let f = common::closed_file();
+    let err = f.close().unwrap_err();
+    assert!(err.advice().contains("bug"));
+}
+
+#[test]
+fn remote_failure_classification() {
+    use close_file::RemoteFailure;
+
+    assert!(RemoteFailure::StaleHandle.is_data_loss_likely());
+    assert!(RemoteFailure::DelayedWriteFailed.is_data_loss_likely());
+    assert!(!RemoteFailure::ConnectionLost.is_data_loss_likely());
+    assert!(RemoteFailure::ConnectionLost.is_transient());
+}
@@ -0,0 +1,99 @@
+use close_file::fs;
+use close_file::Closable;
+use std::io::{Seek, Write};
+
+#[test]
+fn write_read_copy() {
+    let path = std::env::temp_dir().join("close-file-fs");
+    let copy = std::env::temp_dir().join("close-file-fs-copy");
+
+    fs::write(&path, "Hello, world!").unwrap();
+    assert_eq!(fs::read(&path).unwrap(), b"Hello, world!");
+    assert_eq!(fs::copy(&path, &copy).unwrap(), 13);
+    assert_eq!(fs::read_to_string(&copy).unwrap(), "Hello, world!");
+    fs::remove_file(&path).unwrap();
+    fs::remove_file(&copy).unwrap();
+}
+
+#[test]
+fn file() {
+    let path = std::env::temp_dir().join("close-file-fs-file");
+
+    let mut f = fs::File::create(&path).unwrap();
+    assert_eq!(f.path(), path);
+    f.write_all("Hello, world!".as_bytes()).unwrap();
+    f.rewind().unwrap();
+    assert_eq!(f.metadata().unwrap().len(), 13);
+    f.close().unwrap();
+    let f = fs::File::open_with(&path, fs::OpenOptions::new().append(true)).unwrap();
+    close_file::SyncClosable::close_synced(f).unwrap();
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn missing_file() {
+    let err = fs::read("close-file-missing-dir/file").unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn delete_on_failure() {
+    use close_file::{Cleanup, CloseStage, FailurePolicy, SyncClosable};
+
+    let path = std::env::temp_dir().join("close-file-fs-delete-on-failure");
+
+    // Syncing a symlink to procfs fails, removing it leaves procfs untouched
+    std::os::unix::fs::symlink("/proc/self/status", &path).unwrap();
+    let mut f = fs::File::open(&path).unwrap();
+    f.set_failure_policy(FailurePolicy::Delete);
+    let err = f.close_synced().unwrap_err();
+    assert_eq!(err.stage(), CloseStage::Sync);
+    assert!(matches!(err.cleanup(), Some(Cleanup::Removed)));
+    assert!(!err.is_retryable());
+    assert!(err.to_string().ends_with("; the file was removed"));
+    assert!(std::fs::symlink_metadata(&path).is_err());
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn keep_partial_on_failure() {
+    use close_file::{Cleanup, FailurePolicy, SyncClosable};
+
+    let path = std::env::temp_dir().join("close-file-fs-keep-partial");
+    let partial = std::env::temp_dir().join("close-file-fs-keep-partial.partial");
+
+    std::os::unix::fs::symlink("/proc/self/status", &path).unwrap();
+    let mut f = fs::File::open(&path).unwrap();
+    f.set_failure_policy(FailurePolicy::keep_partial());
+    let err = f.close_synced().unwrap_err();
+    match err.cleanup() {
+        Some(Cleanup::Renamed(renamed)) => assert_eq!(renamed, &partial),
+        cleanup => panic!("unexpected cleanup {:?}", cleanup),
+    }
+    assert!(std::fs::symlink_metadata(&path).is_err());
+    std::fs::remove_file(&partial).unwrap();
+}
+
+#[test]
+fn durable_open_options() {
+    use close_file::fs::DurableOpenOptions;
+    use close_file::Durability;
+
+    let path = std::env::temp_dir().join("close-file-durable-open");
+
+    for durability in [Durability::None, Durability::DataSync, Durability::FullSync] {
+        let mut f = DurableOpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .write_through(durability)
+            .open(&path)
+            .unwrap();
+        assert_eq!(f.path(), path);
+        f.write_all("Hello, world!".as_bytes()).unwrap();
+        f.close().unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"Hello, world!");
+    }
+    fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,26 @@
+use std::io::Write;
+
+#[test]
+fn close_guard() {
+    use close_file::CloseGuard;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    let path = std::env::temp_dir().join("close-file-close-guard");
+
+    let (sender, receiver) = std::sync::mpsc::channel();
+    let mut f = CloseGuard::with_sender(std::fs::File::create(&path).unwrap(), sender);
+    f.write_all(b"Hello, world!").unwrap();
+    std::thread::spawn(move || drop(f)).join().unwrap();
+    receiver.recv().unwrap().unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), b"Hello, world!");
+
+    let closed = Arc::new(AtomicBool::new(false));
+    let flag = closed.clone();
+    let f = CloseGuard::with_callback(std::fs::File::open(&path).unwrap(), move |result| {
+        flag.store(result.is_ok(), Ordering::SeqCst)
+    });
+    drop(f);
+    assert!(closed.load(Ordering::SeqCst));
+    std::fs::remove_file(&path).unwrap();
+}
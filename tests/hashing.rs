@@ -0,0 +1,35 @@
+#![cfg(feature = "digest")]
+
+use close_file::{Closable, CloseStage, HashingWriter};
+use sha2::{Digest, Sha256};
+use std::io::Write;
+
+#[test]
+fn close_hashed() {
+    let path = std::env::temp_dir().join("close-file-close-hashed");
+
+    let f = std::fs::File::create(&path).unwrap();
+    let mut w = HashingWriter::<_, Sha256>::new(f);
+    w.write_all(b"Hello, world!").unwrap();
+    assert_eq!(w.close_hashed().unwrap(), Sha256::digest(b"Hello, world!"));
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn expected_digest() {
+    let path = std::env::temp_dir().join("close-file-expected-digest");
+
+    let f = std::fs::File::create(&path).unwrap();
+    let mut w = HashingWriter::<_, Sha256>::new(f).expect(Sha256::digest(b"Hello, world!"));
+    w.write_all(b"Hello, world!").unwrap();
+    w.close().unwrap();
+
+    let f = std::fs::File::create(&path).unwrap();
+    let mut w = HashingWriter::<_, Sha256>::new(f).expect(Sha256::digest(b"Hello, world!"));
+    w.write_all(b"Hello").unwrap();
+    let err = w.close().unwrap_err();
+    assert_eq!(err.stage(), CloseStage::Verify);
+    assert_eq!(err.as_io_error().kind(), std::io::ErrorKind::InvalidData);
+    assert_eq!(std::fs::read(&path).unwrap(), b"Hello");
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,35 @@
+// The only test of this binary, as the handler is global
+
+#[cfg(target_os = "linux")]
+#[test]
+fn drop_close_error_handler() {
+    use close_file::DropCloseError;
+    use std::sync::Mutex;
+
+    static ERRORS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+    fn handler(e: DropCloseError) {
+        assert!(!e.during_unwinding());
+        ERRORS.lock().unwrap().push(format!("{}", e));
+    }
+
+    close_file::set_drop_close_error_handler(handler);
+    // Fails to remove the temporary file, as its directory is gone
+    let dir = std::env::temp_dir().join("close-file-hook-dir");
+    std::fs::create_dir_all(&dir).unwrap();
+    let f = close_file::AtomicWriteFile::create(dir.join("file")).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+    drop(f);
+
+    close_file::set_strict(false);
+    let (reader, _) = std::io::pipe().unwrap();
+    drop(close_file::MustClose::with_drop_action(
+        reader,
+        close_file::DropAction::Log,
+    ));
+    let errors = ERRORS.lock().unwrap();
+    assert_eq!(errors.len(), 2);
+    assert!(errors[0].starts_with("error dropping close_file::atomic::AtomicWriteFile: "));
+    assert!(errors[1].starts_with("error dropping close_file::must_close::MustClose<"));
+    assert!(errors[1].contains("was dropped without being closed"));
+}
@@ -0,0 +1,22 @@
+use close_file::{Closable, LockedFile, SyncClosable};
+use std::io::Write;
+
+#[test]
+fn locked_file() {
+    let path = std::env::temp_dir().join("close-file-locked-file");
+
+    let mut f = LockedFile::lock(std::fs::File::create(&path).unwrap()).unwrap();
+    f.write_all(b"Hello, world!").unwrap();
+    f.close().unwrap();
+
+    // The lock is released, so it can be taken again
+    let f = LockedFile::lock(std::fs::File::open(&path).unwrap()).unwrap();
+    f.unlock().unwrap().close().unwrap();
+
+    let first = LockedFile::lock_shared(std::fs::File::open(&path).unwrap()).unwrap();
+    let second = LockedFile::lock_shared(std::fs::File::open(&path).unwrap()).unwrap();
+    first.close_synced().unwrap();
+    second.close().unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), b"Hello, world!");
+    std::fs::remove_file(&path).unwrap();
+}
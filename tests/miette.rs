@@ -0,0 +1,19 @@
+#![cfg(feature = "miette")]
+
+use close_file::Closable;
+
+mod common;
+
+#[cfg(unix)]
+#[test]
+fn miette_diagnostic() {
+    use miette::Diagnostic;
+
+    let f = common::closed_file();
+    let err = f.close().unwrap_err();
+    assert_eq!(
+        err.code().unwrap().to_string(),
+        "close_file::invalid_descriptor"
+    );
+    assert_eq!(err.help().unwrap().to_string(), err.advice());
+}
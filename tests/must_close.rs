@@ -0,0 +1,39 @@
+use close_file::{Closable, DropAction, MustClose, SyncClosable};
+use std::io::Write;
+
+#[test]
+fn close() {
+    let path = std::env::temp_dir().join("close-file-must-close");
+
+    let mut f = MustClose::new(std::fs::File::create(&path).unwrap());
+    f.write_all(b"Hello, world!").unwrap();
+    f.close_synced().unwrap();
+    MustClose::new(std::fs::File::open(&path).unwrap())
+        .close()
+        .unwrap();
+    let f = MustClose::new(std::fs::File::open(&path).unwrap());
+    drop(f.defuse());
+    assert_eq!(std::fs::read(&path).unwrap(), b"Hello, world!");
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+#[should_panic(expected = "was dropped without being closed")]
+fn drop_panics() {
+    let (reader, _) = std::io::pipe().unwrap();
+    let _reader = MustClose::with_drop_action(reader, DropAction::Panic);
+}
+
+#[test]
+fn drop_ignored() {
+    let (reader, _) = std::io::pipe().unwrap();
+    drop(MustClose::with_drop_action(reader, DropAction::Ignore));
+}
+
+#[cfg(not(feature = "strict"))]
+#[test]
+fn drop_logged() {
+    // Without a drop close error handler, the message is printed to stderr
+    let (reader, _) = std::io::pipe().unwrap();
+    drop(MustClose::with_drop_action(reader, DropAction::Log));
+}
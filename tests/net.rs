@@ -0,0 +1,127 @@
+use close_file::Closable;
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+
+#[test]
+fn close_tcp_stream() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    let (mut server, _) = listener.accept().unwrap();
+
+    client.write_all("Hello, world!".as_bytes()).unwrap();
+    client.close().unwrap();
+    let mut received = String::new();
+    server.read_to_string(&mut received).unwrap();
+    assert_eq!(received, "Hello, world!");
+    server.close().unwrap();
+}
+
+#[test]
+fn graceful_close() {
+    use std::time::Duration;
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    let (mut server, _) = listener.accept().unwrap();
+
+    client.write_all("Hello, world!".as_bytes()).unwrap();
+    let server = std::thread::spawn(move || {
+        let mut received = String::new();
+        server.read_to_string(&mut received).unwrap();
+        server.write_all("Bye".as_bytes()).unwrap();
+        server.close().unwrap();
+        received
+    });
+    close_file::GracefulClose::new(Duration::from_secs(10))
+        .linger(Duration::from_secs(1))
+        .close(client)
+        .unwrap();
+    assert_eq!(server.join().unwrap(), "Hello, world!");
+}
+
+#[test]
+fn graceful_close_sub_second_linger() {
+    use std::time::Duration;
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    let (mut server, _) = listener.accept().unwrap();
+
+    client.write_all("Hello, world!".as_bytes()).unwrap();
+    close_file::GracefulClose::new(Duration::from_secs(10))
+        .drain(false)
+        .linger(Duration::from_millis(500))
+        .close(client)
+        .unwrap();
+    let mut received = String::new();
+    server.read_to_string(&mut received).unwrap();
+    assert_eq!(received, "Hello, world!");
+    // After a reset instead of a graceful close, writing would fail
+    server.write_all("Bye".as_bytes()).unwrap();
+    server.close().unwrap();
+}
+
+#[test]
+fn graceful_close_drain_timeout() {
+    use close_file::{CloseErrorKind, CloseStage};
+    use std::time::Duration;
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    let (_server, _) = listener.accept().unwrap();
+
+    let err = close_file::graceful_close(client, Duration::from_millis(50)).unwrap_err();
+    assert_eq!(err.stage(), CloseStage::Drain);
+    assert_eq!(err.kind(), CloseErrorKind::TimedOut);
+}
+
+#[test]
+fn close_tcp_listener() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    listener.close().unwrap();
+    assert!(TcpStream::connect(addr).is_err());
+}
+
+#[test]
+fn close_udp_socket() {
+    let socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    let addr = socket.local_addr().unwrap();
+    socket.close().unwrap();
+    // The port is free again once the socket is closed
+    std::net::UdpSocket::bind(addr).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn close_unix_sockets() {
+    use std::os::unix::net::{UnixDatagram, UnixStream};
+
+    let (mut a, b) = UnixStream::pair().unwrap();
+    a.write_all("Hello, world!".as_bytes()).unwrap();
+    a.close().unwrap();
+    b.close().unwrap();
+
+    let (a, b) = UnixDatagram::pair().unwrap();
+    a.close().unwrap();
+    b.close().unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn close_and_unlink() {
+    use std::os::unix::net::UnixListener;
+
+    let path = std::env::temp_dir().join("close-file-net-close-and-unlink");
+    let _ = std::fs::remove_file(&path);
+
+    UnixListener::bind(&path).unwrap().close().unwrap();
+    assert!(path.exists());
+    std::fs::remove_file(&path).unwrap();
+
+    let listener = UnixListener::bind(&path).unwrap();
+    close_file::close_and_unlink(listener).unwrap();
+    assert!(!path.exists());
+    UnixListener::bind(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,58 @@
+use close_file::Closable;
+use std::io::Write;
+
+#[cfg(unix)]
+#[test]
+fn close_owned_fd() {
+    use close_file::SyncClosable;
+    use std::os::unix::io::OwnedFd;
+
+    let path = std::env::temp_dir().join("close-file-owned-fd");
+
+    let fd = OwnedFd::from(std::fs::File::create(&path).unwrap());
+    fd.close().unwrap();
+    let fd = OwnedFd::from(std::fs::File::create(&path).unwrap());
+    fd.close_synced().unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let (reader, writer) = std::io::pipe().unwrap();
+    OwnedFd::from(writer).close_synced().unwrap();
+    OwnedFd::from(reader).close().unwrap();
+}
+
+#[cfg(windows)]
+#[test]
+fn close_owned_handle() {
+    use close_file::SyncClosable;
+    use std::os::windows::io::OwnedHandle;
+
+    let path = std::env::temp_dir().join("close-file-owned-handle");
+
+    let handle = OwnedHandle::from(std::fs::File::create(&path).unwrap());
+    handle.close().unwrap();
+    let handle = OwnedHandle::from(std::fs::File::create(&path).unwrap());
+    handle.close_synced().unwrap();
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[cfg(windows)]
+#[test]
+fn close_owned_socket() {
+    use std::os::windows::io::OwnedSocket;
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    OwnedSocket::from(listener).close().unwrap();
+}
+
+#[test]
+fn close_pipe() {
+    use std::io::Read;
+
+    let (mut reader, mut writer) = std::io::pipe().unwrap();
+    writer.write_all("Hello, world!".as_bytes()).unwrap();
+    writer.close().unwrap();
+    let mut received = String::new();
+    reader.read_to_string(&mut received).unwrap();
+    assert_eq!(received, "Hello, world!");
+    reader.close().unwrap();
+}
@@ -0,0 +1,37 @@
+use close_file::probe_durability;
+
+#[test]
+fn probe_temp_dir() {
+    let probe = probe_durability(std::env::temp_dir()).unwrap();
+    assert!(!probe.reason().is_empty());
+}
+
+#[test]
+fn probe_missing_path() {
+    let err = probe_durability("close-file-missing-dir/file").unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn probe_unknown_filesystem() {
+    use close_file::{Confidence, Durability, FileSystem};
+
+    let probe = probe_durability("/proc").unwrap();
+    assert_eq!(probe.filesystem(), FileSystem::Unknown);
+    assert_eq!(probe.confidence(), Confidence::Low);
+    assert_eq!(probe.recommended(), Durability::FullSync);
+}
+
+#[test]
+fn detect_file() {
+    use close_file::{Closer, FileSystem};
+
+    let path = std::env::temp_dir().join("close-file-detect-file");
+
+    let f = std::fs::File::create(&path).unwrap();
+    let filesystem = FileSystem::of(&f).unwrap();
+    assert_eq!(filesystem, FileSystem::of_path(&path).unwrap());
+    Closer::new().auto_durability(true).close(f).unwrap();
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,88 @@
+#![cfg(unix)]
+
+use close_file::Closable;
+use std::io::{Read, Write};
+use std::process::{Command, Stdio};
+
+mod common;
+
+#[test]
+fn close_child_streams() {
+    let mut child = Command::new("cat")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    let mut stdin = child.stdin.take().unwrap();
+    stdin.write_all("Hello, world!".as_bytes()).unwrap();
+    // cat only exits once it reads the end of its input
+    stdin.close().unwrap();
+    let mut stdout = child.stdout.take().unwrap();
+    let mut output = String::new();
+    stdout.read_to_string(&mut output).unwrap();
+    stdout.close().unwrap();
+    child.stderr.take().unwrap().close().unwrap();
+    assert!(child.wait().unwrap().success());
+    assert_eq!(output, "Hello, world!");
+}
+
+#[test]
+fn close_streams() {
+    use close_file::ChildExt;
+
+    let mut child = Command::new("cat")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .as_mut()
+        .unwrap()
+        .write_all("Hello, world!".as_bytes())
+        .unwrap();
+    let teardown = child.close_streams().unwrap();
+    assert!(teardown.is_ok());
+    assert!(teardown.into_result().unwrap().success());
+}
+
+#[test]
+fn close_streams_timeout() {
+    use close_file::ChildExt;
+    use std::time::Duration;
+
+    let mut child = Command::new("sleep").arg("10").spawn().unwrap();
+    let err = child
+        .close_streams_timeout(Duration::from_millis(50))
+        .unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::TimedOut);
+    child.kill().unwrap();
+    let teardown = child
+        .close_streams_timeout(Duration::from_secs(10))
+        .unwrap();
+    assert!(!teardown.status().success());
+}
+
+#[test]
+fn close_streams_timeout_keeps_stdin_error() {
+    use close_file::{ChildExt, CloseError};
+    use std::os::unix::io::OwnedFd;
+    use std::process::ChildStdin;
+    use std::time::Duration;
+
+    let mut child = Command::new("sleep").arg("10").spawn().unwrap();
+    child.stdin = Some(ChildStdin::from(OwnedFd::from(common::closed_file())));
+    let err = child
+        .close_streams_timeout(Duration::from_millis(50))
+        .unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::TimedOut);
+    let stdin = err.into_inner().unwrap().downcast::<CloseError>().unwrap();
+    assert_eq!(stdin.raw_os_error(), Some(libc::EBADF));
+    // The invalid descriptor must not be closed again on drop
+    stdin.leak();
+    child.kill().unwrap();
+    child.wait().unwrap();
+}
@@ -0,0 +1,26 @@
+#![cfg(feature = "raw")]
+
+use close_file::raw::{close_raw_owner, RawOwner};
+use close_file::Closable;
+
+mod common;
+
+#[test]
+fn close_raw() {
+    let path = std::env::temp_dir().join("close-file-raw-close-raw");
+
+    close_raw_owner(std::fs::File::create(&path).unwrap()).unwrap();
+    let (reader, writer) = std::io::pipe().unwrap();
+    (RawOwner(reader), RawOwner(writer)).close().unwrap();
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn close_raw_invalid() {
+    use close_file::CloseErrorKind;
+
+    let f = common::closed_file();
+    let err = close_raw_owner(f).unwrap_err();
+    assert_eq!(err.kind(), CloseErrorKind::InvalidDescriptor);
+}
@@ -0,0 +1,58 @@
+use std::io::Write;
+
+mod common;
+
+#[test]
+fn close_scope() {
+    let path = std::env::temp_dir().join("close-file-close-scope");
+
+    close_file::scope(|s| {
+        let f = s.register(std::fs::File::create(&path)?);
+        f.write_all(b"Hello, world!")?;
+        s.register(std::io::pipe()?);
+        Ok::<_, std::io::Error>(())
+    })
+    .unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), b"Hello, world!");
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn close_scope_errors() {
+    use close_file::CloseError;
+
+    let err = close_file::scope(|s| {
+        s.register(common::closed_file());
+        Ok::<_, CloseError>(())
+    })
+    .unwrap_err();
+    assert_eq!(err.errors().unwrap().len(), 1);
+
+    let err = close_file::scope(|s| {
+        s.register(common::closed_file());
+        Err::<(), _>(std::io::Error::other("failed"))
+    })
+    .unwrap_err();
+    assert_eq!(err.to_string(), "failed");
+}
+
+#[test]
+fn closing() {
+    use close_file::closing;
+
+    let path = std::env::temp_dir().join("close-file-closing");
+
+    let result: std::io::Result<usize> = closing! {
+        let f = std::fs::File::create(&path)?;
+        let (_, writer) = std::io::pipe()?;
+        => {
+            f.write_all(b"Hello, world!")?;
+            writer.write_all(b"Hello, world!")?;
+            Ok(13)
+        }
+    };
+    assert_eq!(result.unwrap(), 13);
+    assert_eq!(std::fs::read(&path).unwrap(), b"Hello, world!");
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,42 @@
+use close_file::{Closable, CloseSequence, Durability};
+
+mod common;
+
+#[test]
+fn run() {
+    let path = std::env::temp_dir().join("close-file-sequence-run");
+
+    let data = std::fs::File::create(&path).unwrap();
+    CloseSequence::new()
+        .then_synced("data", data, Durability::Sync)
+        .then("reader", std::fs::File::open(&path).unwrap())
+        .then_with("manifest", || std::fs::File::open(&path).unwrap().close())
+        .run()
+        .unwrap();
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn abort() {
+    use std::os::unix::io::AsRawFd;
+
+    let data = common::closed_file();
+    let fd = data.as_raw_fd();
+    let mut manifest_written = false;
+    let err = CloseSequence::new()
+        .then("data", data)
+        .then_with("manifest", || {
+            manifest_written = true;
+            Ok(())
+        })
+        .run()
+        .unwrap_err();
+    assert_eq!(err.index(), 0);
+    assert_eq!(err.label(), "data");
+    assert_eq!(err.skipped(), ["manifest"]);
+    assert!(err
+        .to_string()
+        .starts_with(&format!("step data failed: failed to close fd {}", fd)));
+    assert!(!manifest_written);
+}
@@ -0,0 +1,20 @@
+#![cfg(all(feature = "serde", unix))]
+
+use close_file::Closable;
+use std::os::unix::io::AsRawFd;
+
+mod common;
+
+#[test]
+fn serialize() {
+    let f = common::closed_file();
+    let fd = f.as_raw_fd();
+    let err = f.close_with_path("out.txt").unwrap_err();
+    let json = serde_json::to_value(&err).unwrap();
+    assert_eq!(json["kind"], "invalid_descriptor");
+    assert_eq!(json["os_error"], 9); // EBADF
+    assert_eq!(json["fd"], fd);
+    assert_eq!(json["path"], "out.txt");
+    assert_eq!(json["stage"], "close");
+    assert!(json["message"].is_string());
+}
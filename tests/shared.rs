@@ -0,0 +1,23 @@
+#[test]
+fn close_arc() {
+    use close_file::CloseArcError;
+    use std::sync::Arc;
+
+    let path = std::env::temp_dir().join("close-file-close-arc");
+
+    let f = Arc::new(std::fs::File::create(&path).unwrap());
+    let clone = f.clone();
+    let f = match close_file::close_arc(f) {
+        Err(CloseArcError::StillShared(f)) => f,
+        other => panic!("unexpected result: {:?}", other),
+    };
+    drop(clone);
+    close_file::close_arc(f).unwrap();
+
+    let f = Arc::new(std::fs::File::open(&path).unwrap());
+    let clone = f.clone();
+    let thread = std::thread::spawn(move || close_file::release_arc(clone));
+    close_file::release_arc(f).unwrap();
+    thread.join().unwrap().unwrap();
+    std::fs::remove_file(&path).unwrap();
+}
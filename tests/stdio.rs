@@ -0,0 +1,81 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Runs the test in a child process, as closing stdout would hide the output of the test harness
+fn run_child(test: &str, stdout: Stdio) -> std::process::Output {
+    Command::new(std::env::current_exe().unwrap())
+        .args(["--exact", test, "--test-threads", "1"])
+        .env("CLOSE_FILE_STDIO_CHILD", "1")
+        .stdout(stdout)
+        .stderr(Stdio::piped())
+        .output()
+        .unwrap()
+}
+
+fn is_child() -> bool {
+    std::env::var_os("CLOSE_FILE_STDIO_CHILD").is_some()
+}
+
+#[test]
+fn close_stdout() {
+    if is_child() {
+        // Not print!(), which is captured by the test harness
+        std::io::stdout().write_all(b"Hello, world!").unwrap();
+        close_file::close_stdout().unwrap();
+        close_file::close_stdout().unwrap();
+        return;
+    }
+    let output = run_child("close_stdout", Stdio::piped());
+    assert!(output.status.success());
+    // The harness prints before the test runs, but nothing after stdout was closed
+    assert!(output.stdout.ends_with(b"Hello, world!"));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn close_stdout_full() {
+    use close_file::{CloseErrorKind, CloseStage};
+    use std::os::unix::io::AsRawFd;
+
+    if is_child() {
+        let full = std::fs::OpenOptions::new()
+            .write(true)
+            .open("/dev/full")
+            .unwrap();
+        let mut stdout = std::io::stdout();
+        unsafe { libc::dup2(full.as_raw_fd(), libc::STDOUT_FILENO) };
+        stdout.write_all(b"Hello, world!").unwrap();
+        let err = close_file::close_stdout().unwrap_err();
+        assert_eq!(err.stage(), CloseStage::Flush);
+        assert_eq!(err.kind(), CloseErrorKind::OutOfSpace);
+        std::process::exit(3);
+    }
+    let output = run_child("close_stdout_full", Stdio::null());
+    assert_eq!(output.status.code(), Some(3));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn run_full() {
+    use close_file::StdioExit;
+    use std::os::unix::io::AsRawFd;
+    use std::process::ExitCode;
+
+    if is_child() {
+        let code = StdioExit::new().exit_code(4).message("close-file").run(|| {
+            let full = std::fs::OpenOptions::new()
+                .write(true)
+                .open("/dev/full")
+                .unwrap();
+            unsafe { libc::dup2(full.as_raw_fd(), libc::STDOUT_FILENO) };
+            std::io::stdout().write_all(b"Hello, world!").unwrap();
+            ExitCode::SUCCESS
+        });
+        assert_eq!(code, ExitCode::from(4));
+        std::process::exit(3);
+    }
+    let output = run_child("run_full", Stdio::null());
+    assert_eq!(output.status.code(), Some(3));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("close-file: "), "{}", stderr);
+}
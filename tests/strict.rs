@@ -0,0 +1,12 @@
+// The only test of this binary, as strict mode is global
+
+#[test]
+#[should_panic(expected = "was dropped without being closed")]
+fn strict() {
+    let path = std::env::temp_dir().join("close-file-strict");
+
+    close_file::set_strict(true);
+    assert!(close_file::is_strict());
+    let _f = close_file::TrackedFile::create(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,272 @@
+use close_file::SyncClosable;
+use std::io::Write;
+
+mod common;
+
+#[test]
+fn close_synced() {
+    let path = std::env::temp_dir().join("close-file-close-synced");
+
+    let mut f = std::fs::File::create(&path).unwrap();
+    f.write_all("Hello, world!".as_bytes()).unwrap();
+    f.close_synced().unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), b"Hello, world!");
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn close_datasynced() {
+    let path = std::env::temp_dir().join("close-file-close-datasynced");
+
+    let mut f = std::fs::File::create(&path).unwrap();
+    f.write_all("Hello, world!".as_bytes()).unwrap();
+    f.close_datasynced().unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), b"Hello, world!");
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn close_barrier_synced() {
+    let path = std::env::temp_dir().join("close-file-close-barrier-synced");
+
+    let mut f = std::fs::File::create(&path).unwrap();
+    f.write_all("Hello, world!".as_bytes()).unwrap();
+    f.close_barrier_synced().unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), b"Hello, world!");
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn close_with_durability() {
+    use close_file::Durability;
+
+    let path = std::env::temp_dir().join("close-file-close-with");
+
+    for durability in [Durability::None, Durability::Sync, Durability::FullSync] {
+        let mut f = std::fs::File::create(&path).unwrap();
+        f.write_all("Hello, world!".as_bytes()).unwrap();
+        f.close_with(durability).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), b"Hello, world!");
+    }
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn incremental_writeback() {
+    use close_file::{start_writeback, wait_writeback, CloseOptions, Durability};
+
+    let path = std::env::temp_dir().join("close-file-incremental-writeback");
+
+    let mut f = std::fs::File::create(&path).unwrap();
+    for i in 0..4 {
+        f.write_all(&[0; 4096]).unwrap();
+        start_writeback(&f, i * 4096, 4096).unwrap();
+        wait_writeback(&f, i * 4096, 4096).unwrap();
+    }
+    let options = CloseOptions::new()
+        .start_writeback(true)
+        .durability(Durability::DataSync);
+    f.close_with_options(&options).unwrap();
+    assert_eq!(std::fs::metadata(&path).unwrap().len(), 4 * 4096);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn drop_cache() {
+    use close_file::{CloseOptions, Durability};
+
+    let path = std::env::temp_dir().join("close-file-drop-cache");
+
+    let mut f = std::fs::File::create(&path).unwrap();
+    f.write_all(&[1; 4096]).unwrap();
+    let options = CloseOptions::new()
+        .durability(Durability::DataSync)
+        .drop_cache(true);
+    f.close_with_options(&options).unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), vec![1; 4096]);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn read_only_file() {
+    let path = std::env::temp_dir().join("close-file-read-only");
+
+    std::fs::write(&path, "Hello, world!").unwrap();
+    let f = std::fs::File::open(&path).unwrap();
+    f.close_synced().unwrap();
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn pipe_is_not_synced() {
+    use std::os::fd::OwnedFd;
+
+    let (_reader, writer) = std::io::pipe().unwrap();
+    let f = std::fs::File::from(OwnedFd::from(writer));
+    f.close_synced().unwrap();
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn failed_sync_keeps_file_open() {
+    use close_file::CloseStage;
+
+    // procfs files are regular files, but don't support fsync
+    let f = std::fs::File::open("/proc/self/status").unwrap();
+    let err = f.close_synced().unwrap_err();
+    assert_eq!(err.stage(), CloseStage::Sync);
+    assert!(err.as_fd().is_some());
+    err.retry().unwrap();
+}
+
+#[test]
+fn close_all_synced() {
+    let paths: Vec<_> = (0..3)
+        .map(|i| std::env::temp_dir().join(format!("close-file-close-all-synced-{}", i)))
+        .collect();
+
+    let files = paths.iter().map(|path| {
+        let mut f = std::fs::File::create(path).unwrap();
+        f.write_all("Hello, world!".as_bytes()).unwrap();
+        f
+    });
+    let results = close_file::close_all_synced(files);
+    assert_eq!(results.len(), 3);
+    for (path, result) in paths.iter().zip(results) {
+        result.unwrap();
+        assert_eq!(std::fs::read(path).unwrap(), b"Hello, world!");
+        std::fs::remove_file(path).unwrap();
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn close_all_synced_reports_per_file() {
+    let path = std::env::temp_dir().join("close-file-close-all-synced-per-file");
+
+    let good = std::fs::File::create(&path).unwrap();
+    let bad = common::closed_file();
+    let mut results = close_file::close_all_synced(vec![good, bad]);
+    let err = results.pop().unwrap().unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::EBADF));
+    // The invalid descriptor must not be closed again on drop
+    err.leak();
+    results.pop().unwrap().unwrap();
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn close_with_token() {
+    use close_file::{CloseOptions, Durability, TokenClosable};
+
+    let path = std::env::temp_dir().join("close-file-close-with-token");
+
+    let before = std::time::SystemTime::now();
+    let mut f = std::fs::File::create(&path).unwrap();
+    f.write_all("Hello, world!".as_bytes()).unwrap();
+    let token = f
+        .close_with_token(&CloseOptions::new().durability(Durability::Sync))
+        .unwrap();
+    assert_eq!(token.durability(), Durability::Sync);
+    assert_eq!(token.bytes(), Some(13));
+    assert!(token.closed_at() >= before);
+
+    let f = close_file::fs::File::open(&path).unwrap();
+    let token = f.close_with_token(&CloseOptions::new()).unwrap();
+    assert_eq!(token.durability(), Durability::None);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn close_with_metadata() {
+    use close_file::{CloseOptions, Durability, TokenClosable};
+
+    let path = std::env::temp_dir().join("close-file-close-with-metadata");
+
+    let mut f = std::fs::File::create(&path).unwrap();
+    f.write_all("Hello, world!".as_bytes()).unwrap();
+    let receipt = f
+        .close_with_metadata(&CloseOptions::new().durability(Durability::Sync))
+        .unwrap();
+    assert_eq!(receipt.durability(), Durability::Sync);
+    assert_eq!(receipt.len(), 13);
+    let metadata = std::fs::metadata(&path).unwrap();
+    assert_eq!(receipt.modified(), Some(metadata.modified().unwrap()));
+
+    let f = close_file::fs::File::open(&path).unwrap();
+    let receipt = f.close_with_metadata(&CloseOptions::new()).unwrap();
+    assert_eq!(receipt.durability(), Durability::None);
+    assert_eq!(receipt.len(), 13);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn durable_file() {
+    use close_file::{Closable, Durability, DurableFile};
+
+    let path = std::env::temp_dir().join("close-file-durable-file");
+
+    let mut f = DurableFile::new(std::fs::File::create(&path).unwrap(), Durability::Sync);
+    assert!(!f.is_dirty());
+    f.write_all("Hello, world!".as_bytes()).unwrap();
+    assert!(f.is_dirty());
+    f.sync().unwrap();
+    assert!(!f.is_dirty());
+    f.write_all("!".as_bytes()).unwrap();
+    f.close().unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), b"Hello, world!!");
+
+    let f = DurableFile::new(std::fs::File::open(&path).unwrap(), Durability::Sync);
+    assert!(!f.is_dirty());
+    f.close().unwrap();
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn syncing_writer() {
+    use close_file::{Closable, SyncingWriter};
+
+    let path = std::env::temp_dir().join("close-file-syncing-writer");
+
+    let f = std::fs::File::create(&path).unwrap();
+    let mut w = SyncingWriter::new(f).every_bytes(8);
+    w.write_all("Hello, ".as_bytes()).unwrap();
+    assert_eq!(w.unsynced(), 7);
+    w.write_all("world".as_bytes()).unwrap();
+    assert_eq!(w.unsynced(), 12);
+    // The limit was reached, so the next write syncs first
+    w.write_all("!".as_bytes()).unwrap();
+    assert_eq!(w.unsynced(), 1);
+    w.close().unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), b"Hello, world!");
+
+    let f = std::fs::OpenOptions::new()
+        .append(true)
+        .open(&path)
+        .unwrap();
+    let mut w = SyncingWriter::new(f).every(std::time::Duration::from_secs(0));
+    w.write_all("a".as_bytes()).unwrap();
+    w.write_all("b".as_bytes()).unwrap();
+    assert_eq!(w.unsynced(), 1);
+    w.close().unwrap();
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn write_through() {
+    use close_file::{Closable, WriteThrough};
+
+    let path = std::env::temp_dir().join("close-file-write-through");
+
+    let mut w = WriteThrough::new(std::fs::File::create(&path).unwrap());
+    w.write_all("Hello, world!".as_bytes()).unwrap();
+    w.close().unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), b"Hello, world!");
+
+    let f = std::fs::File::create(&path).unwrap();
+    WriteThrough::new(&f).write_all("Hello".as_bytes()).unwrap();
+    f.close().unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), b"Hello");
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,94 @@
+#![cfg(feature = "tokio")]
+
+use close_file::tokio::FileExt;
+use close_file::{AsyncClosable, CloseOptions, Durability};
+use tokio::io::AsyncWriteExt;
+
+mod common;
+
+#[tokio::test]
+async fn close() {
+    let path = std::env::temp_dir().join("close-file-tokio-close");
+
+    let mut f = tokio::fs::File::create(&path).await.unwrap();
+    f.write_all(b"Hello, world!").await.unwrap();
+    f.close().await.unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), b"Hello, world!");
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn close_with_options() {
+    let path = std::env::temp_dir().join("close-file-tokio-close-with-options");
+
+    let mut f = tokio::fs::File::create(&path).await.unwrap();
+    f.write_all(b"Hello, world!").await.unwrap();
+    let options = CloseOptions::new().durability(Durability::Sync);
+    tokio::spawn(f.close_with_options(&options))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), b"Hello, world!");
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn close_error() {
+    let f = tokio::fs::File::from_std(common::closed_file());
+    let err = f.close().await.unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::EBADF));
+    assert_eq!(err.location().file(), file!());
+    err.leak();
+}
+
+#[tokio::test]
+async fn graceful_close() {
+    use close_file::tokio::TcpStreamExt;
+    use close_file::GracefulClose;
+    use std::time::Duration;
+    use tokio::io::AsyncReadExt;
+    use tokio::net::{TcpListener, TcpStream};
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let mut client = TcpStream::connect(listener.local_addr().unwrap())
+        .await
+        .unwrap();
+    let (mut server, _) = listener.accept().await.unwrap();
+
+    client.write_all(b"Hello, world!").await.unwrap();
+    let server = tokio::spawn(async move {
+        let mut received = String::new();
+        server.read_to_string(&mut received).await.unwrap();
+        server.write_all(b"Bye").await.unwrap();
+        server.close().await.unwrap();
+        received
+    });
+    client
+        .close_gracefully(
+            &GracefulClose::new(Duration::from_secs(10)).linger(Duration::from_secs(1)),
+        )
+        .await
+        .unwrap();
+    assert_eq!(server.await.unwrap(), "Hello, world!");
+}
+
+#[tokio::test]
+async fn graceful_close_drain_timeout() {
+    use close_file::{CloseErrorKind, CloseStage};
+    use std::time::Duration;
+    use tokio::net::{TcpListener, TcpStream};
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let client = TcpStream::connect(listener.local_addr().unwrap())
+        .await
+        .unwrap();
+    let (_server, _) = listener.accept().await.unwrap();
+
+    let err = close_file::tokio::graceful_close(client, Duration::from_millis(50))
+        .await
+        .unwrap_err();
+    assert_eq!(err.stage(), CloseStage::Drain);
+    assert_eq!(err.kind(), CloseErrorKind::TimedOut);
+    assert_eq!(err.location().file(), file!());
+}
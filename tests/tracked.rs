@@ -0,0 +1,38 @@
+use close_file::{Closable, SyncClosable, TrackedFile};
+use std::io::{Read, Seek, Write};
+
+#[test]
+fn close() {
+    let path = std::env::temp_dir().join("close-file-tracked");
+
+    let mut f = TrackedFile::create(&path).unwrap();
+    assert_eq!(f.opened_at().line(), line!() - 1);
+    assert_eq!(f.path(), path);
+    f.write_all(b"Hello, world!").unwrap();
+    f.close().unwrap();
+    let mut f = TrackedFile::open(&path).unwrap();
+    let mut content = String::new();
+    f.read_to_string(&mut content).unwrap();
+    f.rewind().unwrap();
+    f.close_synced().unwrap();
+    assert_eq!(content, "Hello, world!");
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn close_error() {
+    let path = std::env::temp_dir().join("close-file-tracked-close-error");
+    let _ = std::fs::remove_file(&path);
+
+    std::os::unix::fs::symlink("/proc/self/status", &path).unwrap();
+    let f = TrackedFile::open(&path).unwrap();
+    let line = line!() - 1;
+    let err = f.close_synced().unwrap_err();
+    assert_eq!(err.path(), Some(path.as_path()));
+    assert_eq!(err.opened_at().unwrap().line(), line);
+    assert!(err
+        .to_string()
+        .contains(&format!("; opened at {}:{}:", file!(), line)));
+    std::fs::remove_file(&path).unwrap();
+}
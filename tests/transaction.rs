@@ -0,0 +1,61 @@
+use close_file::FileTransaction;
+use std::io::Write;
+
+#[test]
+fn commit() {
+    let dir = std::env::temp_dir().join("close-file-txn-commit");
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("data"), "old").unwrap();
+
+    let mut transaction = FileTransaction::new(&dir).unwrap();
+    let staging_dir = transaction.staging_dir().to_path_buf();
+    let data = transaction.create(dir.join("data")).unwrap();
+    data.write_all("Hello, world!".as_bytes()).unwrap();
+    let manifest = transaction.create(dir.join("manifest")).unwrap();
+    manifest.write_all("data".as_bytes()).unwrap();
+    assert_eq!(std::fs::read(dir.join("data")).unwrap(), b"old");
+    assert!(!dir.join("manifest").exists());
+    transaction.commit().unwrap();
+    assert_eq!(std::fs::read(dir.join("data")).unwrap(), b"Hello, world!");
+    assert_eq!(std::fs::read(dir.join("manifest")).unwrap(), b"data");
+    assert!(!staging_dir.exists());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn abort_and_drop() {
+    let dir = std::env::temp_dir().join("close-file-txn-abort");
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let mut transaction = FileTransaction::new(&dir).unwrap();
+    let staging_dir = transaction.staging_dir().to_path_buf();
+    transaction.create(dir.join("data")).unwrap();
+    transaction.abort().unwrap();
+    assert!(!staging_dir.exists());
+    assert!(!dir.join("data").exists());
+
+    let mut transaction = FileTransaction::new(&dir).unwrap();
+    let staging_dir = transaction.staging_dir().to_path_buf();
+    transaction.create(dir.join("data")).unwrap();
+    drop(transaction);
+    assert!(!staging_dir.exists());
+    assert!(!dir.join("data").exists());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn failed_rename_keeps_later_targets() {
+    use close_file::CloseStage;
+
+    let dir = std::env::temp_dir().join("close-file-txn-failed-rename");
+    std::fs::create_dir_all(dir.join("data").join("child")).unwrap();
+
+    let mut transaction = FileTransaction::new(&dir).unwrap();
+    transaction.create(dir.join("data")).unwrap();
+    transaction.create(dir.join("manifest")).unwrap();
+    let err = transaction.commit().unwrap_err();
+    assert_eq!(err.stage(), CloseStage::Rename);
+    assert!(!dir.join("manifest").exists());
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
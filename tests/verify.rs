@@ -0,0 +1,64 @@
+use close_file::{Closable, CloseStage, ExpectedLen};
+use std::io::Write;
+
+#[test]
+fn expected_len() {
+    let path = std::env::temp_dir().join("close-file-expected-len");
+
+    let mut w = ExpectedLen::new(std::fs::File::create(&path).unwrap(), 13);
+    w.write_all(b"Hello, world!").unwrap();
+    assert_eq!(w.written(), 13);
+    w.close().unwrap();
+
+    let mut w = ExpectedLen::new(std::fs::File::create(&path).unwrap(), 13);
+    w.write_all(b"Hello").unwrap();
+    let err = w.close().unwrap_err();
+    assert_eq!(err.stage(), CloseStage::Verify);
+    assert_eq!(err.as_io_error().kind(), std::io::ErrorKind::InvalidData);
+    assert_eq!(err.kind(), close_file::CloseErrorKind::VerificationFailed);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn verify_after_close() {
+    use close_file::{CloseErrorKind, CloseOptions, Durability, SyncClosable, VerifyAfterClose};
+
+    let path = std::env::temp_dir().join("close-file-verify-after-close");
+
+    let mut w = VerifyAfterClose::new(std::fs::File::create(&path).unwrap(), &path);
+    w.write_all(b"Hello, ").unwrap();
+    w.write_all(b"world!").unwrap();
+    w.close_with_options(
+        &CloseOptions::new()
+            .durability(Durability::Sync)
+            .drop_cache(true),
+    )
+    .unwrap();
+
+    // Something else appends to the file before it is read back
+    let f = std::fs::OpenOptions::new()
+        .append(true)
+        .open(&path)
+        .unwrap();
+    let mut w = VerifyAfterClose::new(f, &path);
+    w.write_all(b"!").unwrap();
+    std::fs::OpenOptions::new()
+        .append(true)
+        .open(&path)
+        .unwrap()
+        .write_all(b"?")
+        .unwrap();
+    let err = w.close().unwrap_err();
+    assert_eq!(err.stage(), CloseStage::Verify);
+    assert_eq!(err.kind(), CloseErrorKind::VerificationFailed);
+    assert_eq!(err.path(), Some(path.as_path()));
+
+    let w = VerifyAfterClose::new(
+        std::fs::File::create(&path).unwrap(),
+        path.with_extension("missing"),
+    );
+    let err = w.close().unwrap_err();
+    assert_eq!(err.as_io_error().kind(), std::io::ErrorKind::NotFound);
+    assert_eq!(err.kind(), CloseErrorKind::Other);
+    std::fs::remove_file(&path).unwrap();
+}
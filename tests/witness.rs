@@ -0,0 +1,28 @@
+use std::io::Write;
+
+#[test]
+fn open_closed_witness() {
+    use close_file::{Closed, Durability, Open};
+
+    fn publish(proof: Closed) -> Durability {
+        proof.durability()
+    }
+
+    let path = std::env::temp_dir().join("close-file-open-closed");
+    let mut f = Open::new(std::fs::File::create(&path).unwrap());
+    f.write_all(b"Hello, world!").unwrap();
+    assert_eq!(publish(f.close_synced().unwrap()), Durability::FullSync);
+    assert_eq!(std::fs::read(&path).unwrap(), b"Hello, world!");
+
+    let f = Open::new(std::fs::File::open(&path).unwrap());
+    assert_eq!(publish(f.close().unwrap()), Durability::None);
+    std::fs::remove_file(&path).unwrap();
+
+    // Pipes can't be synced, so the witness doesn't claim they were
+    #[cfg(unix)]
+    {
+        let (_reader, writer) = std::io::pipe().unwrap();
+        let f = Open::new(std::fs::File::from(std::os::fd::OwnedFd::from(writer)));
+        assert_eq!(publish(f.close_synced().unwrap()), Durability::None);
+    }
+}
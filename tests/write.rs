@@ -0,0 +1,83 @@
+use close_file::{append_and_close, write_and_close, Durability, WriteError};
+
+#[test]
+fn write_and_append() {
+    let path = std::env::temp_dir().join("close-file-write-and-close");
+
+    write_and_close(&path, "Hello").unwrap();
+    append_and_close(&path, ", world").unwrap();
+    close_file::append_and_close_with(&path, "!", Durability::Sync).unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), b"Hello, world!");
+    close_file::write_and_close_with(&path, "Bye", Durability::DataSync).unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), b"Bye");
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn open_error() {
+    let err = write_and_close("close-file-missing-dir/file", "Hello, world!").unwrap_err();
+    assert!(matches!(err, WriteError::Open(_)));
+    assert_eq!(err.as_io_error().kind(), std::io::ErrorKind::NotFound);
+    let err = std::io::Error::from(err);
+    assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn write_error() {
+    let err = append_and_close("/dev/full", "Hello, world!").unwrap_err();
+    assert!(matches!(err, WriteError::Write(_)));
+    assert_eq!(err.as_io_error().raw_os_error(), Some(libc::ENOSPC));
+}
+
+#[test]
+fn copy_durable() {
+    let from = std::env::temp_dir().join("close-file-copy-durable-from");
+    let to = std::env::temp_dir().join("close-file-copy-durable-to");
+
+    write_and_close(&from, "Hello, world!").unwrap();
+    assert_eq!(close_file::copy_durable(&from, &to).unwrap(), 13);
+    assert_eq!(std::fs::read(&to).unwrap(), b"Hello, world!");
+    std::fs::remove_file(&from).unwrap();
+    std::fs::remove_file(&to).unwrap();
+}
+
+#[test]
+fn copy_durable_missing_source() {
+    let to = std::env::temp_dir().join("close-file-copy-durable-missing");
+
+    let err = close_file::copy_durable("close-file-missing-dir/file", &to).unwrap_err();
+    assert_eq!(err.as_io_error().kind(), std::io::ErrorKind::NotFound);
+    assert!(!to.exists());
+}
+
+#[test]
+fn with_create_and_file() {
+    use std::io::{Read, Seek, Write};
+
+    let path = std::env::temp_dir().join("close-file-with-create");
+
+    let written = close_file::with_create(&path, |f| {
+        f.write_all(b"Hello, world!")?;
+        Ok(13)
+    })
+    .unwrap();
+    assert_eq!(written, 13);
+    let content = close_file::with_file(&path, |f| {
+        let mut content = String::new();
+        f.read_to_string(&mut content)?;
+        f.rewind()?;
+        f.write_all(b"Howdy")?;
+        Ok(content)
+    })
+    .unwrap();
+    assert_eq!(content, "Hello, world!");
+    assert_eq!(std::fs::read(&path).unwrap(), b"Howdy, world!");
+
+    let err = close_file::with_file(&path, |_| -> std::io::Result<()> {
+        Err(std::io::ErrorKind::InvalidData.into())
+    })
+    .unwrap_err();
+    assert!(matches!(err, WriteError::Write(_)));
+    std::fs::remove_file(&path).unwrap();
+}